@@ -1,13 +1,21 @@
+mod binding;
 mod call;
+mod case;
+mod cases;
+mod context;
+mod data;
+mod data_type;
 mod expression;
 mod function;
+mod list;
+mod locals;
 mod operator;
 mod program;
+mod tuple;
 mod util;
 mod variable;
 mod variable_name;
 
 pub use crate::ast::function::{Function, FunctionParameter, FunctionSignature, Line};
-pub use crate::ast::operator::Operator;
 pub use crate::ast::program::Program;
 pub use crate::ast::variable::{Variable, VariableType};
@@ -0,0 +1,134 @@
+use crate::ast::context::Context;
+use crate::ast::expression::Expression;
+use crate::ast::locals::Locals;
+use crate::ast::util::{checked_type, span_into_default};
+use crate::ast::variable::VariableType;
+use crate::ast::variable_name::VariableName;
+use crate::err::CompileError;
+use crate::parser::{tokenize, Rule};
+use crate::wasm;
+use pest::Span;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+#[derive(Debug, FromPest)]
+#[pest_ast(rule(Rule::binding))]
+pub struct Binding<'a> {
+    pub name: VariableName<'a>,
+    pub expr: Expression<'a>,
+    // the type of the bound value, known once the binding has been validated
+    #[pest_ast(outer(with(span_into_default)))]
+    pub var_type: RefCell<Option<VariableType>>,
+}
+
+// Validate the bindings in the order from binding_order, each one can see the ones before it
+pub fn validate_bindings<'a>(
+    bindings: &[Binding<'a>],
+    ctx: &mut Context<'a, '_>,
+    local_types: &HashMap<&'a str, VariableType>,
+) -> Result<HashMap<&'a str, VariableType>, Box<dyn CompileError>> {
+    let mut local_types = local_types.clone();
+    for i in binding_order(bindings) {
+        let binding = &bindings[i];
+        if let Err(e) = binding.name.check_lowercase() {
+            ctx.diagnostics.error(e);
+        }
+        let var_type = binding.expr.validate(ctx, &local_types)?;
+        *binding.var_type.borrow_mut() = Some(var_type.clone());
+        local_types.insert(binding.name.name, var_type);
+    }
+    Ok(local_types)
+}
+
+// Lower the bindings to local.set instructions, leaving them in scope for the caller to release.
+// Heap addresses are kept on the shadow stack until the function returns.
+pub fn bindings_to_wasm<'a>(
+    bindings: &[Binding<'a>],
+    locals: &mut Locals<'a>,
+) -> Vec<wasm::Expression> {
+    let mut sets = vec![];
+    for i in binding_order(bindings) {
+        let binding = &bindings[i];
+        let var_type = checked_type(&binding.var_type);
+        let value = binding.expr.to_wasm(locals);
+        let value = locals.root(&var_type, value);
+        let index = locals.declare(binding.name.name, var_type);
+        sets.push(wasm!("local.set", index, value));
+    }
+    sets
+}
+
+// The bindings can be written in any order, so one using a binding written after it, as in
+// `where a = b; b = 2`, is moved after that binding. The rest keep their order, which lets a
+// binding shadow an earlier one with the same name. The names a binding uses are found from the
+// tokens of its expression, so a name it shadows itself still counts.
+fn binding_order(bindings: &[Binding]) -> Vec<usize> {
+    let uses: Vec<Vec<usize>> = bindings
+        .iter()
+        .enumerate()
+        .map(|(i, binding)| {
+            let tokens = tokenize(binding.expr.span().as_str());
+            (i + 1..bindings.len())
+                .filter(|&j| {
+                    let name = bindings[j].name.name;
+                    tokens.iter().any(|t| t.text == name)
+                        && !bindings[..=i].iter().any(|b| b.name.name == name)
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut order = vec![];
+    let mut placed = vec![false; bindings.len()];
+    while order.len() < bindings.len() {
+        // there is always one, as the last binding left doesn't use any after it
+        let next = (0..bindings.len())
+            .find(|&i| !placed[i] && uses[i].iter().all(|&j| placed[j]))
+            .unwrap();
+        placed[next] = true;
+        order.push(next);
+    }
+    order
+}
+
+#[derive(Debug, FromPest)]
+#[pest_ast(rule(Rule::let_in))]
+pub struct Let<'a> {
+    pub bindings: Vec<Binding<'a>>,
+    pub body: Expression<'a>,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+    // the type of the body, known once the expression has been validated
+    #[pest_ast(outer(with(span_into_default)))]
+    pub result_type: RefCell<Option<VariableType>>,
+}
+
+impl<'a> Let<'a> {
+    pub fn validate(
+        &self,
+        ctx: &mut Context<'a, '_>,
+        local_types: &HashMap<&'a str, VariableType>,
+    ) -> Result<VariableType, Box<dyn CompileError>> {
+        let local_types = validate_bindings(&self.bindings, ctx, local_types)?;
+        let result_type = self.body.validate(ctx, &local_types)?;
+        *self.result_type.borrow_mut() = Some(result_type.clone());
+        Ok(result_type)
+    }
+
+    pub fn to_wasm(&self, locals: &mut Locals<'a>) -> wasm::Expression {
+        let mut block = vec![
+            wasm!("block"),
+            wasm!("result", checked_type(&self.result_type).to_wasm()),
+        ];
+        block.extend(bindings_to_wasm(&self.bindings, locals));
+        block.push(self.body.to_wasm(locals));
+        locals.release(self.bindings.len());
+        wasm!(block)
+    }
+}
+
+#[derive(Debug, FromPest)]
+#[pest_ast(rule(Rule::where_clause))]
+pub struct Where<'a> {
+    pub bindings: Vec<Binding<'a>>,
+}
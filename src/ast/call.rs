@@ -1,65 +1,76 @@
+use crate::ast::context::Context;
 use crate::ast::expression::Expression;
-use crate::ast::function::{Function, FunctionSignature};
-use crate::ast::variable::{Variable, VariableType};
+use crate::ast::list::List;
+use crate::ast::locals::Locals;
+use crate::ast::tuple::Tuple;
+use crate::ast::util::{span_into_default, type_mismatch, validate_all};
+use crate::ast::variable::{StringLiteral, Variable, VariableType};
 use crate::ast::variable_name::VariableName;
-use crate::err::TypeMismatchError;
+use crate::err::{ArgumentError, CompileError, Location, TypeMismatchError};
 use crate::parser::Rule;
 use crate::{wasm, wasm_dollar};
+use pest::Span;
+use std::cell::RefCell;
 use std::collections::HashMap;
 
 #[derive(Debug, FromPest)]
 #[pest_ast(rule(Rule::argument))]
 pub enum Argument<'a> {
     Expression(Expression<'a>),
-    Literal(Variable),
+    Tuple(Tuple<'a>),
+    Literal(Variable<'a>),
+    String(StringLiteral<'a>),
+    List(List<'a>),
     VariableName(VariableName<'a>),
 }
 
 impl<'a> Argument<'a> {
+    pub fn span(&self) -> &Span<'a> {
+        match self {
+            Argument::Expression(e) => e.span(),
+            Argument::Tuple(t) => &t.span,
+            Argument::Literal(t) => t.span(),
+            Argument::String(s) => &s.span,
+            Argument::List(l) => &l.span,
+            Argument::VariableName(c) => &c.span,
+        }
+    }
+
     pub fn validate(
         &self,
-        current_function: &'a str,
-        globals: &std::collections::HashMap<&'a str, Vec<&Function<'a>>>,
-        signatures: &mut std::collections::HashMap<&'a str, FunctionSignature>,
-        validated: &mut std::collections::HashSet<&'a str>,
-        local_types: &std::collections::HashMap<&'a str, VariableType>,
-    ) -> Result<VariableType, Box<dyn std::error::Error>> {
+        ctx: &mut Context<'a, '_>,
+        local_types: &HashMap<&'a str, VariableType>,
+    ) -> Result<VariableType, Box<dyn CompileError>> {
         match self {
-            Argument::Expression(e) => e.validate(
-                current_function,
-                globals,
-                signatures,
-                validated,
-                local_types,
-            ),
+            Argument::Expression(e) => e.validate(ctx, local_types),
+            Argument::Tuple(t) => t.validate(ctx, local_types),
             Argument::Literal(t) => Ok(t.get_type()),
+            Argument::String(s) => Ok(s.validate(&mut ctx.data)),
+            Argument::List(l) => l.validate(ctx, local_types),
             Argument::VariableName(c) => Call {
-                variable: VariableName { name: c.name },
+                variable: c.clone(),
                 args: vec![],
+                span: c.span.clone(),
+                arg_types: RefCell::new(vec![]),
             }
-            .validate(
-                current_function,
-                globals,
-                signatures,
-                validated,
-                local_types,
-            ),
+            .validate(ctx, local_types),
         }
     }
 
-    pub fn to_wasm(
-        &self,
-        return_type: VariableType,
-        locals_to_arg_index: &HashMap<&str, usize>,
-    ) -> wasm::Expression {
+    pub fn to_wasm(&self, locals: &mut Locals<'a>) -> wasm::Expression {
         match self {
-            Argument::Expression(e) => e.to_wasm(return_type, locals_to_arg_index),
+            Argument::Expression(e) => e.to_wasm(locals),
+            Argument::Tuple(t) => t.to_wasm(locals),
             Argument::Literal(t) => t.to_wasm(),
+            Argument::String(s) => s.to_wasm(),
+            Argument::List(l) => l.to_wasm(locals),
             Argument::VariableName(c) => Call {
-                variable: VariableName { name: c.name },
+                variable: c.clone(),
                 args: vec![],
+                span: c.span.clone(),
+                arg_types: RefCell::new(vec![]),
             }
-            .to_wasm(return_type, locals_to_arg_index),
+            .to_wasm(locals),
         }
     }
 }
@@ -69,81 +80,118 @@ impl<'a> Argument<'a> {
 pub struct Call<'a> {
     pub variable: VariableName<'a>,
     pub args: Vec<Argument<'a>>,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+    // the types of the arguments, known once the call has been validated
+    #[pest_ast(outer(with(span_into_default)))]
+    pub arg_types: RefCell<Vec<VariableType>>,
 }
 
 impl<'a> Call<'a> {
     pub fn validate(
         &self,
-        current_function: &'a str,
-        globals: &std::collections::HashMap<&'a str, Vec<&Function<'a>>>,
-        signatures: &mut std::collections::HashMap<&'a str, FunctionSignature>,
-        validated: &mut std::collections::HashSet<&'a str>,
-        local_types: &std::collections::HashMap<&'a str, VariableType>,
-    ) -> Result<VariableType, Box<dyn std::error::Error>> {
-        let mut arg_types = vec![];
-        for arg in &self.args {
-            arg_types.push(arg.validate(
-                current_function,
-                globals,
-                signatures,
-                validated,
-                local_types,
-            )?);
+        ctx: &mut Context<'a, '_>,
+        local_types: &HashMap<&'a str, VariableType>,
+    ) -> Result<VariableType, Box<dyn CompileError>> {
+        // the function is used even if its arguments or the call turn out to be wrong
+        if !local_types.contains_key(self.variable.name) {
+            ctx.referenced.insert(self.variable.name);
         }
 
+        let results = self
+            .args
+            .iter()
+            .map(|arg| arg.validate(ctx, local_types))
+            .collect();
+        let arg_types = validate_all(results, ctx.diagnostics)?;
+        *self.arg_types.borrow_mut() = arg_types.clone();
+
         match local_types.get(self.variable.name) {
-            Some(v) => return Ok(*v),
-            None => match globals.get(self.variable.name) {
+            Some(v) => return Ok(v.clone()),
+            None => match ctx.functions.get(self.variable.name) {
+                Some(fns) if fns.is_empty() => self.validate_library_call(ctx, &arg_types)?,
                 Some(fns) => {
                     let mut return_types = vec![];
-                    for f in fns.into_iter() {
-                        return_types.push(f.validate(
-                            current_function,
-                            globals,
-                            signatures,
-                            validated,
-                            &arg_types,
-                        )?);
+                    for f in fns.iter() {
+                        return_types.push(f.validate(&self.span, ctx, &arg_types)?);
                     }
                     if return_types.len() > 0 {
-                        for &return_type in return_types.iter() {
-                            if return_types[0] != return_type {
+                        for return_type in return_types.iter() {
+                            if return_types[0] != *return_type {
                                 return Err(Box::new(TypeMismatchError {
-                                    expected: return_types[0],
-                                    got: return_type,
+                                    expected: return_types[0].clone(),
+                                    got: return_type.clone(),
+                                    location: Location::new(&self.span),
                                 }));
                             }
                         }
-                        return Ok(return_types[0]);
+                        return Ok(return_types.swap_remove(0));
                     }
                 }
                 None => {
                     return Err(Box::new(crate::err::FunctionNotFoundError {
                         name: self.variable.name.to_string(),
+                        location: Location::new(&self.variable.span),
                     }))
                 }
             },
         };
 
-        match signatures.get(self.variable.name) {
-            Some(sig) => Ok(sig.return_type),
+        match ctx.signatures.get(self.variable.name) {
+            Some(sig) => Ok(sig.return_type.clone()),
             None => Err(Box::new(crate::err::NoFunctionMatchesError {
                 name: self.variable.name.to_string(),
+                location: Location::new(&self.span),
             })),
         }
     }
 
-    pub fn to_wasm(
+    // library functions have no cases to check the arguments against, only their signature
+    fn validate_library_call(
         &self,
-        return_type: VariableType,
-        locals_to_arg_index: &HashMap<&str, usize>,
-    ) -> wasm::Expression {
-        match locals_to_arg_index.get(self.variable.name) {
+        ctx: &Context<'a, '_>,
+        arg_types: &[VariableType],
+    ) -> Result<(), Box<dyn CompileError>> {
+        let signature = ctx.signatures.get(self.variable.name).unwrap();
+        if arg_types.len() != signature.arg_types.len() {
+            return Err(Box::new(ArgumentError {
+                function_name: self.variable.name.to_string(),
+                expected: signature.arg_types.len(),
+                actual: arg_types.len(),
+                location: Location::new(&self.span),
+            }));
+        }
+        for ((arg, expected), got) in self.args.iter().zip(&signature.arg_types).zip(arg_types) {
+            if !expected.accepts(got) {
+                return Err(type_mismatch(
+                    expected.clone(),
+                    got.clone(),
+                    Location::new(arg.span()),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_wasm(&self, locals: &mut Locals<'a>) -> wasm::Expression {
+        match locals.get(self.variable.name) {
             Some(i) => wasm!("local.get", i),
             None => {
                 let mut call = vec![wasm!("call"), wasm_dollar!(self.variable.name)];
-                for arg in self.args.iter() {
-                    call.push(arg.to_wasm(return_type, locals_to_arg_index));
+                let arg_types = self.arg_types.borrow();
+                for (i, (arg, arg_type)) in self.args.iter().zip(arg_types.iter()).enumerate() {
+                    let value = arg.to_wasm(locals);
+                    // Arguments are kept while the ones after them are evaluated. Variables are
+                    // already kept by whatever bound them, and nothing can be allocated between
+                    // the last argument and the call.
+                    let kept = match arg {
+                        Argument::VariableName(v) => locals.get(v.name).is_some(),
+                        _ => i + 1 == self.args.len(),
+                    };
+                    call.push(match kept {
+                        true => value,
+                        false => locals.root(arg_type, value),
+                    });
                 }
                 wasm!(call)
             }
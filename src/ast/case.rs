@@ -0,0 +1,254 @@
+use crate::ast::cases::check_alternatives;
+use crate::ast::context::Context;
+use crate::ast::expression::Expression;
+use crate::ast::function::FunctionParameter;
+use crate::ast::locals::Locals;
+use crate::ast::util::{checked_type, span_into_default, type_mismatch};
+use crate::ast::variable::{Variable, VariableType};
+use crate::err::{CompileError, Location};
+use crate::parser::Rule;
+use crate::wasm;
+use pest::Span;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+// the scrutinee is kept in a local under a name no variable can have
+const SCRUTINEE: &str = "case";
+
+#[derive(Debug, FromPest)]
+#[pest_ast(rule(Rule::alternative))]
+pub struct Alternative<'a> {
+    pub pattern: FunctionParameter<'a>,
+    pub expr: Expression<'a>,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+}
+
+#[derive(Debug, FromPest)]
+#[pest_ast(rule(Rule::case_of))]
+pub struct Case<'a> {
+    pub scrutinee: Expression<'a>,
+    pub alternatives: Vec<Alternative<'a>>,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+    // the types of the scrutinee and of the alternatives, known once the expression has been
+    // validated
+    #[pest_ast(outer(with(span_into_default)))]
+    pub scrutinee_type: RefCell<Option<VariableType>>,
+    #[pest_ast(outer(with(span_into_default)))]
+    pub result_type: RefCell<Option<VariableType>>,
+}
+
+impl<'a> Case<'a> {
+    pub fn validate(
+        &self,
+        ctx: &mut Context<'a, '_>,
+        local_types: &HashMap<&'a str, VariableType>,
+    ) -> Result<VariableType, Box<dyn CompileError>> {
+        let scrutinee_type = self.scrutinee.validate(ctx, local_types)?;
+        *self.scrutinee_type.borrow_mut() = Some(scrutinee_type.clone());
+
+        let mut result_type: Option<VariableType> = None;
+        for alternative in &self.alternatives {
+            let mut local_types = local_types.clone();
+            alternative
+                .pattern
+                .bind(&scrutinee_type, &ctx.data_types, &mut local_types)?;
+
+            let t = alternative.expr.validate(ctx, &local_types)?;
+            result_type = match result_type {
+                None => Some(t),
+                Some(expected) => match expected.unify(&t) {
+                    Some(unified) => Some(unified),
+                    None => {
+                        return Err(type_mismatch(
+                            expected,
+                            t,
+                            Location::new(alternative.expr.span()),
+                        ))
+                    }
+                },
+            };
+        }
+
+        check_alternatives(
+            &self.span,
+            &self.alternatives,
+            &scrutinee_type,
+            &ctx.data_types,
+            ctx.diagnostics,
+        );
+
+        let result_type = result_type.unwrap();
+        *self.result_type.borrow_mut() = Some(result_type.clone());
+        Ok(result_type)
+    }
+
+    pub fn to_wasm(&self, locals: &mut Locals<'a>) -> wasm::Expression {
+        let scrutinee_type = checked_type(&self.scrutinee_type);
+        let result_type = checked_type(&self.result_type);
+        let value = self.scrutinee.to_wasm(locals);
+        let value = locals.root(&scrutinee_type, value);
+        let index = locals.declare(SCRUTINEE, scrutinee_type);
+
+        // the alternatives are known to cover every input, so nothing after a catch-all is needed
+        let mut patterns = vec![];
+        let mut catch_all = None;
+        for alternative in &self.alternatives {
+            match &alternative.pattern {
+                FunctionParameter::Variable(_) | FunctionParameter::Wildcard(_) => {
+                    catch_all = Some(alternative);
+                    break;
+                }
+                pattern => patterns.push((pattern, alternative)),
+            }
+        }
+
+        let mut block = vec![
+            wasm!("block"),
+            wasm!("result", result_type.to_wasm()),
+            wasm!("local.set", index, value),
+        ];
+        match (table(&patterns), catch_all) {
+            (Some((min, targets)), Some(default)) => {
+                block.push(self.table_to_wasm(index, min, targets, &patterns, locals));
+                block.push(alternative_to_wasm(default, index, &result_type, locals));
+            }
+            _ => block.push(self.ifs_to_wasm(index, &patterns, catch_all, locals)),
+        }
+
+        locals.release(1);
+        wasm!(block)
+    }
+
+    // tests each pattern in turn, with whatever reaches the end matching the last alternative
+    fn ifs_to_wasm(
+        &self,
+        index: usize,
+        patterns: &[(&FunctionParameter<'a>, &Alternative<'a>)],
+        catch_all: Option<&Alternative<'a>>,
+        locals: &mut Locals<'a>,
+    ) -> wasm::Expression {
+        let result_type = checked_type(&self.result_type);
+        let mut patterns = patterns.to_vec();
+        let mut inner = match catch_all {
+            Some(alternative) => alternative_to_wasm(alternative, index, &result_type, locals),
+            None => alternative_to_wasm(patterns.pop().unwrap().1, index, &result_type, locals),
+        };
+        while let Some((pattern, alternative)) = patterns.pop() {
+            let condition = pattern
+                .wat_condition(&format!("(local.get {})", index))
+                .unwrap();
+            inner = wasm!(
+                "if",
+                wasm!("result", result_type.to_wasm()),
+                wasm::Expression::new(condition),
+                wasm!(
+                    "then",
+                    alternative_to_wasm(alternative, index, &result_type, locals)
+                ),
+                wasm!("else", inner)
+            );
+        }
+        inner
+    }
+
+    // Jumps straight to the alternative for the scrutinee with a br_table. Each literal gets a
+    // block, and breaking out of it runs its alternative and breaks out of the case. Breaking
+    // out of the outermost block runs the catch-all, which follows it.
+    fn table_to_wasm(
+        &self,
+        index: usize,
+        min: i64,
+        targets: Vec<usize>,
+        literals: &[(&FunctionParameter<'a>, &Alternative<'a>)],
+        locals: &mut Locals<'a>,
+    ) -> wasm::Expression {
+        let result_type = checked_type(&self.result_type);
+        let mut br_table = vec![wasm!("br_table")];
+        br_table.extend(targets.into_iter().map(wasm::Expression::new));
+        br_table.push(wasm!(
+            "i32.sub",
+            wasm!("local.get", index),
+            wasm!("i32.const", min)
+        ));
+
+        let mut inner = wasm!("block", br_table);
+        for (i, (_, alternative)) in literals.iter().enumerate() {
+            inner = wasm!(
+                "block",
+                inner,
+                wasm!(
+                    "br",
+                    literals.len() - i,
+                    alternative_to_wasm(alternative, index, &result_type, locals)
+                )
+            );
+        }
+        inner
+    }
+}
+
+fn alternative_to_wasm<'a>(
+    alternative: &Alternative<'a>,
+    index: usize,
+    result_type: &VariableType,
+    locals: &mut Locals<'a>,
+) -> wasm::Expression {
+    match &alternative.pattern {
+        FunctionParameter::Variable(v) => {
+            locals.parameter(v.name, index);
+            let expr = alternative.expr.to_wasm(locals);
+            locals.release(1);
+            expr
+        }
+        pattern => {
+            let mut sets = vec![];
+            let in_scope = pattern.scope(&format!("(local.get {})", index), locals, &mut sets);
+            let expr = alternative.expr.to_wasm(locals);
+            locals.release(in_scope);
+            if sets.is_empty() {
+                return expr;
+            }
+
+            let mut block = vec![wasm!("block"), wasm!("result", result_type.to_wasm())];
+            block.extend(sets);
+            block.push(expr);
+            wasm!(block)
+        }
+    }
+}
+
+// When the patterns are int literals dense enough for a br_table, the smallest of them and the
+// block to break to for each value from there, followed by the default. Values without a literal
+// and those out of range break to the catch-all, after the last literal's block.
+fn table(patterns: &[(&FunctionParameter, &Alternative)]) -> Option<(i64, Vec<usize>)> {
+    let mut values = vec![];
+    for (pattern, _) in patterns {
+        let l = match pattern {
+            FunctionParameter::Literal(l) => l,
+            _ => return None,
+        };
+        match l {
+            Variable::Int(i) => values.push(i64::from(i.val)),
+            Variable::Char(c) => values.push(i64::from(u32::from(c.val))),
+            Variable::Bool(_)
+            | Variable::Double(_)
+            | Variable::Float(_)
+            | Variable::Int64(_)
+            | Variable::Unit(_) => return None,
+        }
+    }
+
+    let (min, max) = (*values.iter().min()?, *values.iter().max()?);
+    if values.len() < 4 || max - min + 1 > 2 * values.len() as i64 {
+        return None;
+    }
+
+    let mut targets = vec![values.len(); (max - min + 1) as usize];
+    for (i, value) in values.iter().enumerate().rev() {
+        targets[(value - min) as usize] = i;
+    }
+    targets.push(values.len());
+    Some((min, targets))
+}
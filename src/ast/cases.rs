@@ -0,0 +1,268 @@
+use crate::ast::case::Alternative;
+use crate::ast::data_type::DataTypes;
+use crate::ast::function::{Function, FunctionParameter};
+use crate::ast::variable::VariableType;
+use crate::err::{
+    CaseMissingError, CaseUnreachableError, Diagnostics, FunctionCaseMissingError,
+    FunctionCaseUnreachableError, Location,
+};
+use pest::Span;
+
+// What a parameter matches, as far as checking the cases of a function goes. Literals are
+// constructors without any arguments, `(x : xs)` is the constructor `:` with two, a tuple is the
+// only constructor of its type, and data types have the constructors they were declared with.
+#[derive(Clone, PartialEq)]
+enum Pattern {
+    Any,
+    Constructor(String, Vec<Pattern>),
+}
+
+impl Pattern {
+    // Whether the pattern could match a value of the type. Those that can't have already been
+    // reported as type errors.
+    fn fits(&self, var_type: &VariableType, data_types: &DataTypes) -> bool {
+        match (self, constructors(var_type, data_types)) {
+            (Pattern::Any, _) => true,
+            (Pattern::Constructor(name, args), Some(all)) => all.iter().any(|(n, arg_types)| {
+                n == name
+                    && args.len() == arg_types.len()
+                    && args
+                        .iter()
+                        .zip(arg_types)
+                        .all(|(a, t)| a.fits(t, data_types))
+            }),
+            (Pattern::Constructor(_, args), None) => args.is_empty(),
+        }
+    }
+
+    fn new(parameter: &FunctionParameter) -> Self {
+        match parameter {
+            FunctionParameter::Literal(l) => Pattern::Constructor(l.to_string(), vec![]),
+            FunctionParameter::EmptyList(_) => Pattern::Constructor("[]".to_string(), vec![]),
+            FunctionParameter::Cons(c) => Pattern::Constructor(
+                ":".to_string(),
+                vec![Pattern::new(&c.head), Pattern::new(&c.tail)],
+            ),
+            FunctionParameter::Tuple(t) => Pattern::Constructor(
+                tuple_name(t.elements.len()),
+                t.elements.iter().map(Pattern::new).collect(),
+            ),
+            FunctionParameter::Constructor(c) => Pattern::Constructor(
+                c.name.name.to_string(),
+                c.arguments.iter().map(Pattern::new).collect(),
+            ),
+            FunctionParameter::Variable(_) | FunctionParameter::Wildcard(_) => Pattern::Any,
+        }
+    }
+}
+
+// the constructor of tuples with `arity` elements, such as `(,)` for pairs
+fn tuple_name(arity: usize) -> String {
+    format!("({})", ",".repeat(arity - 1))
+}
+
+// Every constructor of the type, along with the types of its arguments, for the types that can
+// be covered by listing them all
+fn constructors(
+    var_type: &VariableType,
+    data_types: &DataTypes,
+) -> Option<Vec<(String, Vec<VariableType>)>> {
+    let constant = |name: &str| (name.to_string(), vec![]);
+    match var_type {
+        VariableType::Bool => Some(vec![constant("true"), constant("false")]),
+        VariableType::Unit => Some(vec![constant("()")]),
+        VariableType::List(element_type) => Some(vec![
+            constant("[]"),
+            (
+                ":".to_string(),
+                vec![*element_type.clone(), var_type.clone()],
+            ),
+        ]),
+        VariableType::Tuple(types) => Some(vec![(tuple_name(types.len()), types.clone())]),
+        VariableType::Data(name) => data_types.constructors_of(name).map(|all| {
+            all.into_iter()
+                .map(|(name, c)| (name.to_string(), c.arg_types.clone()))
+                .collect()
+        }),
+        _ => None,
+    }
+}
+
+// the types of the arguments of a constructor of the type
+fn arguments(var_type: &VariableType, name: &str, data_types: &DataTypes) -> Vec<VariableType> {
+    constructors(var_type, data_types)
+        .and_then(|all| all.into_iter().find(|(n, _)| n == name))
+        .map_or(vec![], |(_, arguments)| arguments)
+}
+
+// Report cases that can never be reached because the cases above them match everything they
+// would, and functions where some input is not matched by any case.
+pub fn check_cases(
+    name: &str,
+    functions: &[&Function],
+    arg_types: &[VariableType],
+    data_types: &DataTypes,
+    diagnostics: &mut Diagnostics,
+) {
+    let rows: Vec<(Vec<Pattern>, bool)> = functions
+        .iter()
+        .map(|f| {
+            let row = f.parameters.iter().map(Pattern::new).collect();
+            (row, f.guard.is_some())
+        })
+        .collect();
+    let (unreachable, missing) = check_rows(&rows, arg_types, data_types);
+
+    for i in unreachable {
+        diagnostics.error(Box::new(FunctionCaseUnreachableError {
+            function_name: name.to_string(),
+            location: Location::new(&functions[i].span),
+        }));
+    }
+    if missing {
+        diagnostics.error(Box::new(FunctionCaseMissingError {
+            function_name: name.to_string(),
+            location: Location::new(&functions[0].name.span),
+        }));
+    }
+}
+
+// The same checks for the alternatives of a `case` expression
+pub fn check_alternatives(
+    case: &Span,
+    alternatives: &[Alternative],
+    scrutinee_type: &VariableType,
+    data_types: &DataTypes,
+    diagnostics: &mut Diagnostics,
+) {
+    let rows: Vec<(Vec<Pattern>, bool)> = alternatives
+        .iter()
+        .map(|a| (vec![Pattern::new(&a.pattern)], false))
+        .collect();
+    let (unreachable, missing) =
+        check_rows(&rows, std::slice::from_ref(scrutinee_type), data_types);
+
+    for i in unreachable {
+        diagnostics.error(Box::new(CaseUnreachableError {
+            location: Location::new(&alternatives[i].span),
+        }));
+    }
+    if missing {
+        let start = case.start_pos();
+        diagnostics.error(Box::new(CaseMissingError {
+            location: Location::new(&start.span(&start)),
+        }));
+    }
+}
+
+// Takes each row of patterns, and whether a guard may stop it from applying, returning the
+// rows that can never be reached and whether some input is not matched by any row.
+fn check_rows(
+    rows: &[(Vec<Pattern>, bool)],
+    types: &[VariableType],
+    data_types: &DataTypes,
+) -> (Vec<usize>, bool) {
+    let fits = |row: &Vec<Pattern>| row.iter().zip(types).all(|(p, t)| p.fits(t, data_types));
+    if !rows.iter().all(|(row, _)| fits(row)) {
+        return (vec![], false);
+    }
+
+    let mut unreachable = vec![];
+    let mut matched: Vec<Vec<Pattern>> = vec![];
+    for (i, (row, guarded)) in rows.iter().enumerate() {
+        if !useful(&matched, row, types, data_types) {
+            unreachable.push(i);
+        }
+        // a guard may not hold, so a guarded row can't be relied on to match anything
+        if !guarded {
+            matched.push(row.to_vec());
+        }
+    }
+
+    let missing = useful(
+        &matched,
+        &vec![Pattern::Any; types.len()],
+        types,
+        data_types,
+    );
+    (unreachable, missing)
+}
+
+// Whether some input matched by `row` is not matched by any of `rows`
+fn useful(
+    rows: &[Vec<Pattern>],
+    row: &[Pattern],
+    types: &[VariableType],
+    data_types: &DataTypes,
+) -> bool {
+    let (first, rest) = match row.split_first() {
+        Some(split) => split,
+        None => return rows.is_empty(),
+    };
+
+    match first {
+        Pattern::Constructor(name, args) => {
+            let mut row = args.to_vec();
+            row.extend_from_slice(rest);
+            let mut arg_types = arguments(&types[0], name, data_types);
+            arg_types.extend_from_slice(&types[1..]);
+            useful(
+                &specialize(rows, name, args.len()),
+                &row,
+                &arg_types,
+                data_types,
+            )
+        }
+        Pattern::Any => {
+            let mut used: Vec<&String> = vec![];
+            for r in rows {
+                if let Pattern::Constructor(name, _) = &r[0] {
+                    if !used.contains(&name) {
+                        used.push(name);
+                    }
+                }
+            }
+
+            // when every constructor is used, the row is useful if it is for any one of them
+            if let Some(all) = constructors(&types[0], data_types) {
+                if all.len() == used.len() {
+                    return all.into_iter().any(|(name, mut arg_types)| {
+                        let arity = arg_types.len();
+                        let mut row = vec![Pattern::Any; arity];
+                        row.extend_from_slice(rest);
+                        arg_types.extend_from_slice(&types[1..]);
+                        useful(
+                            &specialize(rows, &name, arity),
+                            &row,
+                            &arg_types,
+                            data_types,
+                        )
+                    });
+                }
+            }
+
+            let defaults: Vec<Vec<Pattern>> = rows
+                .iter()
+                .filter(|r| r[0] == Pattern::Any)
+                .map(|r| r[1..].to_vec())
+                .collect();
+            useful(&defaults, rest, &types[1..], data_types)
+        }
+    }
+}
+
+// The rows that can match the constructor in the first column, with that column replaced by the
+// patterns for its arguments
+fn specialize(rows: &[Vec<Pattern>], name: &str, arity: usize) -> Vec<Vec<Pattern>> {
+    rows.iter()
+        .filter_map(|r| {
+            let mut row = match &r[0] {
+                Pattern::Any => vec![Pattern::Any; arity],
+                Pattern::Constructor(n, args) if n == name => args.to_vec(),
+                Pattern::Constructor(_, _) => return None,
+            };
+            row.extend_from_slice(&r[1..]);
+            Some(row)
+        })
+        .collect()
+}
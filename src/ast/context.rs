@@ -0,0 +1,18 @@
+use crate::ast::data::Data;
+use crate::ast::data_type::DataTypes;
+use crate::ast::function::{Function, FunctionSignature};
+use crate::err::Diagnostics;
+use std::collections::{HashMap, HashSet};
+
+// State shared by the type checker while it walks the program.
+pub struct Context<'a, 'p> {
+    pub functions: &'p HashMap<&'a str, Vec<&'p Function<'a>>>,
+    pub signatures: HashMap<&'a str, FunctionSignature>,
+    pub validated: HashSet<&'a str>,
+    pub failed: HashSet<&'a str>,
+    // functions something calls, even if the call itself turns out to be wrong
+    pub referenced: HashSet<&'a str>,
+    pub diagnostics: &'p mut Diagnostics,
+    pub data: Data,
+    pub data_types: DataTypes<'a>,
+}
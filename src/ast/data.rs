@@ -0,0 +1,58 @@
+use crate::wasm;
+use std::collections::HashMap;
+
+// Static data laid out in memory from `start` when the module is instantiated, such as the
+// contents of string literals.
+pub struct Data {
+    end: usize,
+    strings: HashMap<String, usize>,
+}
+
+impl Data {
+    pub fn new(start: usize) -> Self {
+        Self {
+            end: start,
+            strings: HashMap::new(),
+        }
+    }
+
+    // the first address after the data
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    // the address of the string, adding it unless an equal string is already there
+    pub fn string(&mut self, s: &str) -> usize {
+        if let Some(&address) = self.strings.get(s) {
+            return address;
+        }
+        let address = self.end;
+        self.end += s.len();
+        self.strings.insert(s.to_string(), address);
+        address
+    }
+
+    pub fn to_wasm(&self) -> Vec<wasm::Expression> {
+        let mut strings: Vec<(&String, &usize)> = self.strings.iter().collect();
+        strings.sort_by_key(|(_, &address)| address);
+        strings
+            .into_iter()
+            .filter(|(s, _)| !s.is_empty())
+            .map(|(s, address)| wasm!("data", wasm!("i32.const", address), quote(s)))
+            .collect()
+    }
+}
+
+// a wat string holding the bytes of `s`, escaping anything that isn't printable ascii
+fn quote(s: &str) -> wasm::Expression {
+    let mut quoted = String::from("\"");
+    for b in s.bytes() {
+        match b {
+            b'"' | b'\\' => quoted.push_str(&format!("\\{}", b as char)),
+            0x20..=0x7e => quoted.push(b as char),
+            _ => quoted.push_str(&format!("\\{:02x}", b)),
+        }
+    }
+    quoted.push('"');
+    wasm!(quoted)
+}
@@ -0,0 +1,190 @@
+use crate::ast::function::{FunctionSignature, VarType};
+use crate::ast::util::span_into_str;
+use crate::ast::variable::VariableType;
+use crate::err::{
+    Diagnostics, FunctionAlreadyDefinedError, Location, NotImplementedError,
+    TypeAlreadyDefinedError,
+};
+use crate::parser::Rule;
+use crate::stdlib::{keep, pop_frame, push_frame, set_field, tuple, MAX_TUPLE_ELEMENTS};
+use crate::{wasm, wasm_dollar};
+use pest::Span;
+use std::collections::HashMap;
+
+// the name of a data type or one of its constructors
+#[derive(Debug, FromPest, Clone)]
+#[pest_ast(rule(Rule::type_name))]
+pub struct TypeName<'a> {
+    #[pest_ast(outer(with(span_into_str)))]
+    pub name: &'a str,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+}
+
+// `data Shape = Circle float | Rect float float`
+#[derive(Debug, FromPest)]
+#[pest_ast(rule(Rule::data))]
+pub struct DataDeclaration<'a> {
+    pub name: TypeName<'a>,
+    pub constructors: Vec<ConstructorDeclaration<'a>>,
+}
+
+// `Rect float float`, the name of a constructor followed by the types of its arguments
+#[derive(Debug, FromPest)]
+#[pest_ast(rule(Rule::constructor))]
+pub struct ConstructorDeclaration<'a> {
+    pub name: TypeName<'a>,
+    pub types: Vec<VarType<'a>>,
+}
+
+// A constructor of a data type. Its values are cells on the heap holding its tag, the index of
+// the constructor in the declaration, followed by its arguments.
+#[derive(Debug, Clone)]
+pub struct Constructor<'a> {
+    pub type_name: &'a str,
+    pub tag: usize,
+    pub arg_types: Vec<VariableType>,
+    // where the constructor is declared
+    pub span: Span<'a>,
+}
+
+impl<'a> Constructor<'a> {
+    // the types of the fields of the cells the constructor makes
+    pub fn cell_types(&self) -> Vec<VariableType> {
+        let mut types = vec![VariableType::Int];
+        types.extend(self.arg_types.iter().cloned());
+        types
+    }
+
+    // constructors are called like any other function
+    pub fn signature(&self) -> FunctionSignature {
+        FunctionSignature {
+            arg_types: self.arg_types.clone(),
+            return_type: VariableType::Data(self.type_name.to_string()),
+        }
+    }
+
+    // The function making a cell for the constructor from its arguments. Those on the heap are
+    // kept on the shadow stack while the cell is allocated.
+    pub fn to_wasm(&self, name: &str) -> wasm::Expression {
+        let types = self.cell_types();
+        let cell = self.arg_types.len();
+        let frame = cell + 1;
+        let heap_args: Vec<usize> = (0..self.arg_types.len())
+            .filter(|&i| self.arg_types[i].is_heap())
+            .collect();
+
+        let mut func = vec![wasm!("func"), wasm_dollar!(name)];
+        if !self.arg_types.is_empty() {
+            let mut param = vec![wasm!("param")];
+            param.extend(self.arg_types.iter().map(VariableType::to_wasm));
+            func.push(wasm!(param));
+        }
+        func.push(wasm!("result", "i32"));
+        func.push(wasm!("local", "i32", "i32"));
+
+        if !heap_args.is_empty() {
+            func.push(wasm!("local.set", frame, push_frame(heap_args.len())));
+            for (slot, &i) in heap_args.iter().enumerate() {
+                func.push(wasm!(
+                    "drop",
+                    keep(wasm!("local.get", frame), slot, wasm!("local.get", i))
+                ));
+            }
+        }
+        func.push(wasm!("local.set", cell, tuple(&types)));
+        func.push(set_field(
+            &types,
+            0,
+            wasm!("local.get", cell),
+            wasm!("i32.const", self.tag),
+        ));
+        for i in 0..self.arg_types.len() {
+            func.push(set_field(
+                &types,
+                i + 1,
+                wasm!("local.get", cell),
+                wasm!("local.get", i),
+            ));
+        }
+        if !heap_args.is_empty() {
+            func.push(pop_frame(wasm!("local.get", frame)));
+        }
+        func.push(wasm!("local.get", cell));
+        wasm!(func)
+    }
+}
+
+// The data types declared by the program, along with their constructors
+#[derive(Default)]
+pub struct DataTypes<'a> {
+    // the names of the constructors of each type, in the order they were declared
+    pub types: HashMap<&'a str, Vec<&'a str>>,
+    pub constructors: HashMap<&'a str, Constructor<'a>>,
+}
+
+impl<'a> DataTypes<'a> {
+    pub fn declare(&mut self, declaration: &DataDeclaration<'a>, diagnostics: &mut Diagnostics) {
+        let type_name = declaration.name.name;
+        if self.types.contains_key(type_name) {
+            diagnostics.error(Box::new(TypeAlreadyDefinedError {
+                name: type_name.to_string(),
+                location: Location::new(&declaration.name.span),
+            }));
+            return;
+        }
+
+        let mut names = vec![];
+        for c in &declaration.constructors {
+            if self.constructors.contains_key(c.name.name) {
+                diagnostics.error(Box::new(FunctionAlreadyDefinedError {
+                    function_name: c.name.name.to_string(),
+                    location: Location::new(&c.name.span),
+                }));
+                continue;
+            }
+            // one field of the cell holds the tag
+            if c.types.len() >= MAX_TUPLE_ELEMENTS {
+                diagnostics.error(Box::new(NotImplementedError {
+                    sub: format!(
+                        "constructors with more than {} arguments",
+                        MAX_TUPLE_ELEMENTS - 1
+                    ),
+                    location: Location::new(&c.name.span),
+                }));
+                continue;
+            }
+            let constructor = Constructor {
+                type_name,
+                tag: names.len(),
+                arg_types: c.types.iter().map(|t| t.var_type.clone()).collect(),
+                span: c.name.span.clone(),
+            };
+            self.constructors.insert(c.name.name, constructor);
+            names.push(c.name.name);
+        }
+        self.types.insert(type_name, names);
+    }
+
+    // the constructors of the data type, in the order of their tags, if it is declared
+    pub fn constructors_of(&self, type_name: &str) -> Option<Vec<(&'a str, &Constructor<'a>)>> {
+        self.types.get(type_name).map(|names| {
+            names
+                .iter()
+                .map(|&name| (name, &self.constructors[name]))
+                .collect()
+        })
+    }
+
+    // the first data type the type refers to that isn't declared, if there is one
+    pub fn undeclared(&self, var_type: &VariableType) -> Option<String> {
+        match var_type {
+            VariableType::Data(name) if !self.types.contains_key(name.as_str()) => {
+                Some(name.to_string())
+            }
+            VariableType::List(element_type) => self.undeclared(element_type),
+            VariableType::Tuple(types) => types.iter().find_map(|t| self.undeclared(t)),
+            _ => None,
+        }
+    }
+}
@@ -1,124 +1,338 @@
+use crate::ast::binding::Let;
 use crate::ast::call::Call;
-use crate::ast::function::{Function, FunctionSignature};
-use crate::ast::operator::Operator;
-use crate::ast::variable::{Variable, VariableType};
-use crate::err::{OperatorArgumentError, TypeMismatchError};
+use crate::ast::case::Case;
+use crate::ast::context::Context;
+use crate::ast::list::List;
+use crate::ast::locals::Locals;
+use crate::ast::operator::{Operator, PrefixOperator};
+use crate::ast::tuple::Tuple;
+use crate::ast::util::{checked_type, span_into_default, type_mismatch, validate_all};
+use crate::ast::variable::{StringLiteral, Variable, VariableType};
+use crate::err::{
+    CompileError, ListElementError, Location, OperatorArgumentError, TypeMismatchError,
+};
 use crate::parser::Rule;
+use crate::stdlib::cons;
 use crate::wasm;
-use std::collections::{HashMap, HashSet};
+use from_pest::{ConversionError, FromPest, Void};
+use pest::iterators::Pairs;
+use pest::Span;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::iter::Peekable;
+use std::vec::IntoIter;
 
 #[derive(Debug, FromPest)]
 #[pest_ast(rule(Rule::unary))]
 pub enum Unary<'a> {
     Expression(Expression<'a>),
-    Literal(Variable),
+    Tuple(Tuple<'a>),
+    Literal(Variable<'a>),
+    String(StringLiteral<'a>),
+    List(List<'a>),
+    Prefix(Box<Prefix<'a>>),
     Call(Call<'a>),
 }
 
 impl<'a> Unary<'a> {
+    pub fn span(&self) -> &Span<'a> {
+        match self {
+            Unary::Expression(e) => e.span(),
+            Unary::Tuple(t) => &t.span,
+            Unary::Literal(t) => t.span(),
+            Unary::String(s) => &s.span,
+            Unary::List(l) => &l.span,
+            Unary::Prefix(p) => &p.span,
+            Unary::Call(c) => &c.span,
+        }
+    }
+
     pub fn validate(
         &self,
-        current_function: &'a str,
-        globals: &HashMap<&'a str, Vec<&Function<'a>>>,
-        signatures: &mut HashMap<&'a str, FunctionSignature>,
-        validated: &mut HashSet<&'a str>,
+        ctx: &mut Context<'a, '_>,
         local_types: &HashMap<&'a str, VariableType>,
-    ) -> Result<VariableType, Box<dyn std::error::Error>> {
+    ) -> Result<VariableType, Box<dyn CompileError>> {
         match self {
-            Unary::Expression(e) => e.validate(
-                current_function,
-                globals,
-                signatures,
-                validated,
-                local_types,
-            ),
+            Unary::Expression(e) => e.validate(ctx, local_types),
+            Unary::Tuple(t) => t.validate(ctx, local_types),
             Unary::Literal(t) => Ok(t.get_type()),
-            Unary::Call(c) => c.validate(
-                current_function,
-                globals,
-                signatures,
-                validated,
-                local_types,
-            ),
+            Unary::String(s) => Ok(s.validate(&mut ctx.data)),
+            Unary::List(l) => l.validate(ctx, local_types),
+            Unary::Prefix(p) => p.validate(ctx, local_types),
+            Unary::Call(c) => c.validate(ctx, local_types),
         }
     }
 
-    pub fn to_wasm(
-        &self,
-        return_type: VariableType,
-        locals_to_arg_index: &HashMap<&str, usize>,
-    ) -> wasm::Expression {
+    pub fn to_wasm(&self, locals: &mut Locals<'a>) -> wasm::Expression {
         match self {
-            Unary::Expression(e) => e.to_wasm(return_type, locals_to_arg_index),
+            Unary::Expression(e) => e.to_wasm(locals),
+            Unary::Tuple(t) => t.to_wasm(locals),
             Unary::Literal(t) => t.to_wasm(),
-            Unary::Call(c) => c.to_wasm(return_type, locals_to_arg_index),
+            Unary::String(s) => s.to_wasm(),
+            Unary::List(l) => l.to_wasm(locals),
+            Unary::Prefix(p) => p.to_wasm(locals),
+            Unary::Call(c) => c.to_wasm(locals),
         }
     }
 }
 
 #[derive(Debug, FromPest)]
-#[pest_ast(rule(Rule::binary))]
+#[pest_ast(rule(Rule::prefix))]
+pub struct Prefix<'a> {
+    pub operator: PrefixOperator,
+    pub operand: Unary<'a>,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+    // the type of the operand, known once the expression has been validated
+    #[pest_ast(outer(with(span_into_default)))]
+    pub operand_type: RefCell<Option<VariableType>>,
+}
+
+impl<'a> Prefix<'a> {
+    pub fn validate(
+        &self,
+        ctx: &mut Context<'a, '_>,
+        local_types: &HashMap<&'a str, VariableType>,
+    ) -> Result<VariableType, Box<dyn CompileError>> {
+        let operand_type = self.operand.validate(ctx, local_types)?;
+        *self.operand_type.borrow_mut() = Some(operand_type.clone());
+        match (self.operator, &operand_type) {
+            (PrefixOperator::Not(_), VariableType::Bool) => Ok(VariableType::Bool),
+            (PrefixOperator::Negate(_), t) if t.is_number() => Ok(operand_type),
+            _ => Err(Box::new(OperatorArgumentError {
+                operator: self.operator.to_string(),
+                argument_type: operand_type,
+                location: Location::new(&self.span),
+            })),
+        }
+    }
+
+    pub fn to_wasm(&self, locals: &mut Locals<'a>) -> wasm::Expression {
+        let operand_type = checked_type(&self.operand_type);
+        let operand = self.operand.to_wasm(locals);
+        match (self.operator, &operand_type) {
+            (PrefixOperator::Negate(_), t) if t.is_float() => {
+                wasm!(format!("{}.neg", t.to_wasm()), operand)
+            }
+            (PrefixOperator::Negate(_), t) => wasm!(
+                format!("{}.sub", t.to_wasm()),
+                wasm!(format!("{}.const", t.to_wasm()), 0),
+                operand
+            ),
+            (PrefixOperator::Not(_), _) => wasm!("i32.eqz", operand),
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct Binary<'a> {
-    pub left: Unary<'a>,
+    pub left: Expression<'a>,
     pub operator: Operator,
-    pub right: Unary<'a>,
+    pub right: Expression<'a>,
+    pub span: Span<'a>,
+    // the type of both operands, or of the elements of the list for `:`, known once the
+    // expression has been validated
+    pub operand_type: RefCell<Option<VariableType>>,
+}
+
+// The grammar parses a flat list of operands and operators, the tree is built here by precedence climbing
+impl<'a> FromPest<'a> for Binary<'a> {
+    type Rule = Rule;
+    type FatalError = Void;
+
+    fn from_pest(pest: &mut Pairs<'a, Rule>) -> Result<Self, ConversionError<Void>> {
+        let mut clone = pest.clone();
+        let pair = clone.next().ok_or(ConversionError::NoMatch)?;
+        if pair.as_rule() != Rule::binary {
+            return Err(ConversionError::NoMatch);
+        }
+
+        let mut inner = pair.into_inner();
+        let first = Unary::from_pest(&mut inner)?;
+        let mut rest = vec![];
+        while inner.peek().is_some() {
+            rest.push((
+                Operator::from_pest(&mut inner)?,
+                Unary::from_pest(&mut inner)?,
+            ));
+        }
+
+        *pest = clone;
+        match Binary::climb(
+            Expression::Unary(Box::new(first)),
+            &mut rest.into_iter().peekable(),
+            0,
+        ) {
+            Expression::Binary(b) => Ok(*b),
+            _ => Err(ConversionError::NoMatch),
+        }
+    }
 }
 
 impl<'a> Binary<'a> {
+    fn climb(
+        mut left: Expression<'a>,
+        rest: &mut Peekable<IntoIter<(Operator, Unary<'a>)>>,
+        min_precedence: u8,
+    ) -> Expression<'a> {
+        while let Some((operator, right)) = rest.next_if(|(o, _)| o.precedence() >= min_precedence)
+        {
+            let mut right = Expression::Unary(Box::new(right));
+            if let Some((next, _)) = rest.peek() {
+                if next.precedence() > operator.precedence() {
+                    right = Binary::climb(right, rest, operator.precedence() + 1);
+                } else if next.precedence() == operator.precedence()
+                    && operator.is_right_associative()
+                {
+                    right = Binary::climb(right, rest, operator.precedence());
+                }
+            }
+            left = Expression::Binary(Box::new(Binary {
+                span: left.span().start_pos().span(&right.span().end_pos()),
+                left,
+                operator,
+                right,
+                operand_type: RefCell::new(None),
+            }));
+        }
+        left
+    }
+
     pub fn validate(
         &self,
-        current_function: &'a str,
-        globals: &HashMap<&'a str, Vec<&Function<'a>>>,
-        signatures: &mut HashMap<&'a str, FunctionSignature>,
-        validated: &mut HashSet<&'a str>,
+        ctx: &mut Context<'a, '_>,
         local_types: &HashMap<&'a str, VariableType>,
-    ) -> Result<VariableType, Box<dyn std::error::Error>> {
-        let left_type = self.left.validate(
-            current_function,
-            globals,
-            signatures,
-            validated,
-            local_types,
-        )?;
-        let right_type = self.right.validate(
-            current_function,
-            globals,
-            signatures,
-            validated,
-            local_types,
-        )?;
+    ) -> Result<VariableType, Box<dyn CompileError>> {
+        let left_type = self.left.validate(ctx, local_types);
+        let right_type = self.right.validate(ctx, local_types);
+        let (left_type, right_type) = (left_type?, right_type?);
+        if let Operator::Cons(_) = self.operator {
+            return self.validate_cons(left_type, right_type);
+        }
+        // an operand the operator can't take is reported as such, rather than as a mismatch with
+        // the other operand
+        let result_type = match self.result_type(&left_type) {
+            Some(t) => t,
+            None => {
+                return Err(Box::new(OperatorArgumentError {
+                    operator: self.operator.to_string(),
+                    argument_type: left_type,
+                    location: Location::new(&self.span),
+                }))
+            }
+        };
         if left_type != right_type {
             return Err(Box::new(TypeMismatchError {
                 expected: left_type,
                 got: right_type,
+                location: Location::new(self.right.span()),
             }));
         }
-        Ok(match self.operator {
-            Operator::Add(_)
-            | Operator::Subtract(_)
-            | Operator::Multiply(_)
-            | Operator::Divide(_) => match left_type {
-                VariableType::Bool => {
-                    return Err(Box::new(OperatorArgumentError {
-                        operator: self.operator,
-                        argument_type: left_type,
-                    }))
-                }
-                _ => left_type,
-            },
-            Operator::Eq(_) | Operator::Neq(_) => VariableType::Bool,
-        })
+        *self.operand_type.borrow_mut() = Some(left_type);
+        Ok(result_type)
     }
 
-    pub fn to_wasm(
+    // the type of the result when both operands have the given type, if the operator takes it
+    fn result_type(&self, operand_type: &VariableType) -> Option<VariableType> {
+        match (self.operator, operand_type) {
+            (
+                Operator::Add(_)
+                | Operator::Subtract(_)
+                | Operator::Multiply(_)
+                | Operator::Divide(_),
+                t,
+            ) if t.is_number() => Some(operand_type.clone()),
+            (
+                Operator::Modulo(_)
+                | Operator::BitAnd(_)
+                | Operator::BitOr(_)
+                | Operator::BitXor(_)
+                | Operator::ShiftLeft(_)
+                | Operator::ShiftRight(_),
+                t,
+            ) if t.is_integer() => Some(operand_type.clone()),
+            (Operator::Lt(_) | Operator::Lte(_) | Operator::Gt(_) | Operator::Gte(_), t)
+                if t.is_number() || *t == VariableType::Char =>
+            {
+                Some(VariableType::Bool)
+            }
+            // lists would only be compared by their address
+            (Operator::Eq(_) | Operator::Neq(_), t) if !t.is_heap() => Some(VariableType::Bool),
+            (Operator::And(_) | Operator::Or(_), VariableType::Bool) => Some(VariableType::Bool),
+            _ => None,
+        }
+    }
+
+    // `x : xs` puts x in front of a list of the same type
+    fn validate_cons(
         &self,
-        return_type: VariableType,
-        locals_to_arg_index: &HashMap<&str, usize>,
-    ) -> wasm::Expression {
+        left_type: VariableType,
+        right_type: VariableType,
+    ) -> Result<VariableType, Box<dyn CompileError>> {
+        let element_type = match &right_type {
+            VariableType::List(element_type) => element_type,
+            _ => {
+                return Err(Box::new(OperatorArgumentError {
+                    operator: self.operator.to_string(),
+                    argument_type: right_type,
+                    location: Location::new(self.right.span()),
+                }))
+            }
+        };
+        match element_type.unify(&left_type) {
+            Some(t) => {
+                *self.operand_type.borrow_mut() = Some(t.clone());
+                Ok(VariableType::List(Box::new(t)))
+            }
+            None => Err(Box::new(ListElementError {
+                expected: *element_type.clone(),
+                got: left_type,
+                location: Location::new(self.left.span()),
+            })),
+        }
+    }
+
+    pub fn to_wasm(&self, locals: &mut Locals<'a>) -> wasm::Expression {
+        let operand_type = checked_type(&self.operand_type);
+        let left = self.left.to_wasm(locals);
+        if let Operator::Cons(_) = self.operator {
+            // the element is kept while the list is evaluated
+            let head = locals.root(&operand_type, left);
+            return cons(&operand_type, head, self.right.to_wasm(locals));
+        }
+        let right = self.right.to_wasm(locals);
+
+        // the right hand side of a logical operator is only evaluated when it can change the result
+        match self.operator {
+            Operator::And(_) => {
+                return wasm!(
+                    "if",
+                    wasm!("result", "i32"),
+                    left,
+                    wasm!("then", right),
+                    wasm!("else", wasm!("i32.const", 0))
+                )
+            }
+            Operator::Or(_) => {
+                return wasm!(
+                    "if",
+                    wasm!("result", "i32"),
+                    left,
+                    wasm!("then", wasm!("i32.const", 1)),
+                    wasm!("else", right)
+                )
+            }
+            _ => {}
+        }
+
         wasm!(
-            format!("{}.{}", return_type.to_wasm(), self.operator.to_wasm()),
-            self.left.to_wasm(return_type, locals_to_arg_index),
-            self.right.to_wasm(return_type, locals_to_arg_index)
+            format!(
+                "{}.{}",
+                operand_type.to_wasm(),
+                self.operator.to_wasm(&operand_type)
+            ),
+            left,
+            right
         )
     }
 }
@@ -129,67 +343,55 @@ pub struct Ternary<'a> {
     pub condition: Unary<'a>,
     pub truthy: Unary<'a>,
     pub falsy: Unary<'a>,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+    // the type of both branches, known once the expression has been validated
+    #[pest_ast(outer(with(span_into_default)))]
+    pub result_type: RefCell<Option<VariableType>>,
 }
 
 impl<'a> Ternary<'a> {
     pub fn validate(
         &self,
-        current_function: &'a str,
-        globals: &HashMap<&'a str, Vec<&Function<'a>>>,
-        signatures: &mut HashMap<&'a str, FunctionSignature>,
-        validated: &mut HashSet<&'a str>,
+        ctx: &mut Context<'a, '_>,
         local_types: &HashMap<&'a str, VariableType>,
-    ) -> Result<VariableType, Box<dyn std::error::Error>> {
-        let return_type = self.condition.validate(
-            current_function,
-            globals,
-            signatures,
-            validated,
-            local_types,
-        )?;
+    ) -> Result<VariableType, Box<dyn CompileError>> {
+        let results = vec![
+            self.condition.validate(ctx, local_types),
+            self.truthy.validate(ctx, local_types),
+            self.falsy.validate(ctx, local_types),
+        ];
+        let types = validate_all(results, ctx.diagnostics)?;
+        let (return_type, truthy_type, falsy_type) =
+            (types[0].clone(), types[1].clone(), types[2].clone());
         if return_type != VariableType::Bool {
             return Err(Box::new(TypeMismatchError {
                 expected: VariableType::Bool,
                 got: return_type,
+                location: Location::new(self.condition.span()),
             }));
         }
-        let truthy_type = self.truthy.validate(
-            current_function,
-            globals,
-            signatures,
-            validated,
-            local_types,
-        )?;
-        let falsy_type = self.falsy.validate(
-            current_function,
-            globals,
-            signatures,
-            validated,
-            local_types,
-        )?;
-        if truthy_type != falsy_type {
-            return Err(Box::new(TypeMismatchError {
-                expected: truthy_type,
-                got: falsy_type,
-            }));
-        }
-        Ok(truthy_type)
+        let result_type = match truthy_type.unify(&falsy_type) {
+            Some(t) => t,
+            None => {
+                return Err(type_mismatch(
+                    truthy_type,
+                    falsy_type,
+                    Location::new(self.falsy.span()),
+                ))
+            }
+        };
+        *self.result_type.borrow_mut() = Some(result_type.clone());
+        Ok(result_type)
     }
 
-    pub fn to_wasm(
-        &self,
-        return_type: VariableType,
-        locals_to_arg_index: &HashMap<&str, usize>,
-    ) -> wasm::Expression {
+    pub fn to_wasm(&self, locals: &mut Locals<'a>) -> wasm::Expression {
         wasm!(
             "if",
-            wasm!("result", return_type.to_wasm()),
-            self.condition.to_wasm(return_type, locals_to_arg_index),
-            wasm!(
-                "then",
-                self.truthy.to_wasm(return_type, locals_to_arg_index)
-            ),
-            wasm!("else", self.falsy.to_wasm(return_type, locals_to_arg_index))
+            wasm!("result", checked_type(&self.result_type).to_wasm()),
+            self.condition.to_wasm(locals),
+            wasm!("then", self.truthy.to_wasm(locals)),
+            wasm!("else", self.falsy.to_wasm(locals))
         )
     }
 }
@@ -200,57 +402,42 @@ pub enum Expression<'a> {
     Unary(Box<Unary<'a>>),
     Binary(Box<Binary<'a>>),
     Ternary(Box<Ternary<'a>>),
+    Let(Box<Let<'a>>),
+    Case(Box<Case<'a>>),
 }
 
 impl<'a> Expression<'a> {
+    pub fn span(&self) -> &Span<'a> {
+        match self {
+            Expression::Unary(u) => u.span(),
+            Expression::Binary(b) => &b.span,
+            Expression::Ternary(t) => &t.span,
+            Expression::Let(l) => &l.span,
+            Expression::Case(c) => &c.span,
+        }
+    }
+
     pub fn validate(
         &self,
-        current_function: &'a str,
-        globals: &HashMap<&'a str, Vec<&Function<'a>>>,
-        signatures: &mut HashMap<&'a str, FunctionSignature>,
-        validated: &mut HashSet<&'a str>,
+        ctx: &mut Context<'a, '_>,
         local_types: &HashMap<&'a str, VariableType>,
-    ) -> Result<VariableType, Box<dyn std::error::Error>> {
+    ) -> Result<VariableType, Box<dyn CompileError>> {
         match self {
-            Expression::Unary(u) => u.validate(
-                current_function,
-                globals,
-                signatures,
-                validated,
-                local_types,
-            ),
-            Expression::Binary(b) => b.validate(
-                current_function,
-                globals,
-                signatures,
-                validated,
-                local_types,
-            ),
-            Expression::Ternary(t) => t.validate(
-                current_function,
-                globals,
-                signatures,
-                validated,
-                local_types,
-            ),
+            Expression::Unary(u) => u.validate(ctx, local_types),
+            Expression::Binary(b) => b.validate(ctx, local_types),
+            Expression::Ternary(t) => t.validate(ctx, local_types),
+            Expression::Let(l) => l.validate(ctx, local_types),
+            Expression::Case(c) => c.validate(ctx, local_types),
         }
     }
 
-    pub fn to_wasm(
-        &self,
-        return_type: VariableType,
-        locals_to_arg_index: &HashMap<&str, usize>,
-    ) -> wasm::Expression {
+    pub fn to_wasm(&self, locals: &mut Locals<'a>) -> wasm::Expression {
         match self {
-            Expression::Unary(u) => {
-                return u.to_wasm(return_type, locals_to_arg_index);
-            }
-            Expression::Binary(b) => {
-                return b.to_wasm(return_type, locals_to_arg_index);
-            }
-            Expression::Ternary(t) => {
-                return t.to_wasm(return_type, locals_to_arg_index);
-            }
+            Expression::Unary(u) => u.to_wasm(locals),
+            Expression::Binary(b) => b.to_wasm(locals),
+            Expression::Ternary(t) => t.to_wasm(locals),
+            Expression::Let(l) => l.to_wasm(locals),
+            Expression::Case(c) => c.to_wasm(locals),
         }
     }
 }
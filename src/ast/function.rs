@@ -1,40 +1,392 @@
+use crate::ast::binding::{bindings_to_wasm, validate_bindings, Where};
+use crate::ast::cases::check_cases;
+use crate::ast::context::Context;
+use crate::ast::data_type::{Constructor, DataDeclaration, DataTypes, TypeName};
 use crate::ast::expression::Expression;
+use crate::ast::locals::Locals;
+use crate::ast::util::{checked_type, span_into_default, span_into_phantomdata, type_mismatch};
 use crate::ast::variable::{Variable, VariableType};
 use crate::ast::variable_name::VariableName;
-use crate::err::{ArgumentError, TypeMismatchError, UntypedFunctionError};
+use crate::err::{
+    ArgumentError, CompileError, ConstructorNotFoundError, Location, ReportedError,
+    TypeMismatchError, UntypedFunctionError,
+};
 use crate::parser::Rule;
+use crate::stdlib::{field, head, tail};
 use crate::wasm;
 use pest::Span;
-use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
 
 #[derive(Debug, FromPest)]
 #[pest_ast(rule(Rule::parameter))]
 pub enum FunctionParameter<'a> {
     Variable(VariableName<'a>),
-    Literal(Variable),
+    Literal(Variable<'a>),
+    Wildcard(Wildcard),
+    EmptyList(EmptyList<'a>),
+    Cons(Box<ConsPattern<'a>>),
+    Tuple(TuplePattern<'a>),
+    Constructor(ConstructorPattern<'a>),
 }
 
-fn span_into_variable_type(span: Span) -> VariableType {
-    match span.as_str() {
+#[derive(Debug, FromPest)]
+#[pest_ast(rule(Rule::wildcard))]
+pub struct Wildcard {
+    #[pest_ast(outer(with(span_into_phantomdata)))]
+    n: PhantomData<()>,
+}
+
+// `[]`, which only matches the empty list
+#[derive(Debug, FromPest)]
+#[pest_ast(rule(Rule::empty_list))]
+pub struct EmptyList<'a> {
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+}
+
+// `(x : xs)`, which matches a list that isn't empty when its first element matches the first
+// pattern and the rest of it matches the second
+#[derive(Debug, FromPest)]
+#[pest_ast(rule(Rule::cons_pattern))]
+pub struct ConsPattern<'a> {
+    pub head: FunctionParameter<'a>,
+    pub tail: FunctionParameter<'a>,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+    // the type of the elements of the list, known once the pattern has been validated
+    #[pest_ast(outer(with(span_into_default)))]
+    pub element_type: RefCell<Option<VariableType>>,
+}
+
+// `(x, y)`, which matches a tuple when each of its elements matches the pattern in its place
+#[derive(Debug, FromPest)]
+#[pest_ast(rule(Rule::tuple_pattern))]
+pub struct TuplePattern<'a> {
+    pub elements: Vec<FunctionParameter<'a>>,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+    // the type of the tuple, known once the pattern has been validated
+    #[pest_ast(outer(with(span_into_default)))]
+    pub tuple_type: RefCell<Option<VariableType>>,
+}
+
+// `(Circle r)`, or `Empty` for a constructor without arguments, which matches a value made by the
+// constructor when each of its arguments matches the pattern in its place
+#[derive(Debug, FromPest)]
+#[pest_ast(rule(Rule::constructor_pattern))]
+pub struct ConstructorPattern<'a> {
+    pub name: TypeName<'a>,
+    pub arguments: Vec<FunctionParameter<'a>>,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+    // the constructor the name refers to, known once the pattern has been validated
+    #[pest_ast(outer(with(span_into_default)))]
+    pub constructor: RefCell<Option<Constructor<'a>>>,
+}
+
+impl<'a> FunctionParameter<'a> {
+    // Checks the pattern can match a value of the type, adding the variables it binds to
+    // `local_types`.
+    pub fn bind(
+        &self,
+        var_type: &VariableType,
+        data_types: &DataTypes<'a>,
+        local_types: &mut HashMap<&'a str, VariableType>,
+    ) -> Result<(), Box<dyn CompileError>> {
+        let (got, span) = match self {
+            FunctionParameter::Variable(v) => {
+                local_types.insert(v.name, var_type.clone());
+                return Ok(());
+            }
+            FunctionParameter::Wildcard(_) => return Ok(()),
+            FunctionParameter::Literal(l) if l.get_type() == *var_type => return Ok(()),
+            FunctionParameter::Literal(l) => (l.get_type(), l.span()),
+            FunctionParameter::EmptyList(e) => match var_type {
+                VariableType::List(_) => return Ok(()),
+                _ => (VariableType::List(Box::new(VariableType::Unknown)), &e.span),
+            },
+            FunctionParameter::Cons(c) => match var_type {
+                VariableType::List(element_type) => {
+                    *c.element_type.borrow_mut() = Some(*element_type.clone());
+                    c.head.bind(element_type, data_types, local_types)?;
+                    return c.tail.bind(var_type, data_types, local_types);
+                }
+                _ => (VariableType::List(Box::new(VariableType::Unknown)), &c.span),
+            },
+            FunctionParameter::Tuple(t) => match var_type {
+                VariableType::Tuple(types) if types.len() == t.elements.len() => {
+                    *t.tuple_type.borrow_mut() = Some(var_type.clone());
+                    for (element, element_type) in t.elements.iter().zip(types) {
+                        element.bind(element_type, data_types, local_types)?;
+                    }
+                    return Ok(());
+                }
+                _ => (
+                    VariableType::Tuple(vec![VariableType::Unknown; t.elements.len()]),
+                    &t.span,
+                ),
+            },
+            FunctionParameter::Constructor(c) => {
+                let constructor = match data_types.constructors.get(c.name.name) {
+                    Some(constructor) => constructor,
+                    None => {
+                        return Err(Box::new(ConstructorNotFoundError {
+                            name: c.name.name.to_string(),
+                            location: Location::new(&c.name.span),
+                        }))
+                    }
+                };
+                let got = VariableType::Data(constructor.type_name.to_string());
+                if got != *var_type {
+                    (got, &c.span)
+                } else if c.arguments.len() != constructor.arg_types.len() {
+                    return Err(Box::new(ArgumentError {
+                        function_name: c.name.name.to_string(),
+                        expected: constructor.arg_types.len(),
+                        actual: c.arguments.len(),
+                        location: Location::new(&c.span),
+                    }));
+                } else {
+                    *c.constructor.borrow_mut() = Some(constructor.clone());
+                    for (argument, arg_type) in c.arguments.iter().zip(&constructor.arg_types) {
+                        argument.bind(arg_type, data_types, local_types)?;
+                    }
+                    return Ok(());
+                }
+            }
+        };
+        Err(type_mismatch(var_type.clone(), got, Location::new(span)))
+    }
+
+    // the conditions for the pattern to match `value`, the wat of a value it is checked against
+    fn wat_conditions(&self, value: &str, conditions: &mut Vec<String>) {
+        match self {
+            FunctionParameter::Variable(_) | FunctionParameter::Wildcard(_) => {}
+            FunctionParameter::Literal(l) => {
+                conditions.push(format!(
+                    r#"({}.eq
+    {}
+    {}
+)"#,
+                    l.get_type().to_wasm(),
+                    value,
+                    l.to_wasm(),
+                ));
+            }
+            FunctionParameter::EmptyList(_) => {
+                conditions.push(format!(
+                    r#"(i32.eqz
+    {}
+)"#,
+                    value
+                ));
+            }
+            // the elements are only looked at once the list is known not to be empty
+            FunctionParameter::Cons(c) => {
+                let mut elements = vec![];
+                c.head.wat_conditions(&c.head_wat(value), &mut elements);
+                c.tail.wat_conditions(&c.tail_wat(value), &mut elements);
+                let not_empty = format!(
+                    r#"(i32.ne
+    {}
+    (i32.const 0)
+)"#,
+                    value
+                );
+                conditions.push(wat_and_then(not_empty, &mut elements));
+            }
+            // the arguments are only looked at once the value is known to have been made by the
+            // constructor, as other constructors may have fewer
+            FunctionParameter::Constructor(c) => {
+                let constructor = c.checked_constructor();
+                let mut arguments = vec![];
+                for (i, argument) in c.arguments.iter().enumerate() {
+                    argument.wat_conditions(&c.field_wat(value, i + 1), &mut arguments);
+                }
+                let tagged = format!(
+                    r#"(i32.eq
+    {}
+    (i32.const {})
+)"#,
+                    c.field_wat(value, 0),
+                    constructor.tag
+                );
+                conditions.push(wat_and_then(tagged, &mut arguments));
+            }
+            // a tuple is never 0, so its elements can be looked at straight away
+            FunctionParameter::Tuple(t) => {
+                for (i, element) in t.elements.iter().enumerate() {
+                    element.wat_conditions(&t.field_wat(value, i), conditions);
+                }
+            }
+        }
+    }
+
+    // the wat for whether the pattern matches `value`, or None when it matches everything
+    pub fn wat_condition(&self, value: &str) -> Option<String> {
+        let mut conditions = vec![];
+        self.wat_conditions(value, &mut conditions);
+        match conditions.is_empty() {
+            true => None,
+            false => Some(combine_wat_conditions(&mut conditions)),
+        }
+    }
+
+    // the patterns inside this one, along with the wat of the part of `value` each one matches
+    // and its type
+    fn parts(&self, value: &str) -> Vec<(&FunctionParameter<'a>, String, VariableType)> {
+        match self {
+            FunctionParameter::Cons(c) => {
+                let element_type = checked_type(&c.element_type);
+                vec![
+                    (&c.head, c.head_wat(value), element_type.clone()),
+                    (
+                        &c.tail,
+                        c.tail_wat(value),
+                        VariableType::List(Box::new(element_type)),
+                    ),
+                ]
+            }
+            FunctionParameter::Tuple(t) => match checked_type(&t.tuple_type) {
+                VariableType::Tuple(types) => t
+                    .elements
+                    .iter()
+                    .zip(types)
+                    .enumerate()
+                    .map(|(i, (element, element_type))| {
+                        (element, t.field_wat(value, i), element_type)
+                    })
+                    .collect(),
+                _ => unreachable!(),
+            },
+            FunctionParameter::Constructor(c) => c
+                .arguments
+                .iter()
+                .zip(c.checked_constructor().arg_types)
+                .enumerate()
+                .map(|(i, (argument, arg_type))| (argument, c.field_wat(value, i + 1), arg_type))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    // Brings the variables inside the lists, tuples and constructors of the pattern into scope,
+    // where `value` is the wat of the value it matched, returning how many there are to release. Each is
+    // loaded into a local of its own by an instruction added to `sets`.
+    pub fn scope(
+        &self,
+        value: &str,
+        locals: &mut Locals<'a>,
+        sets: &mut Vec<wasm::Expression>,
+    ) -> usize {
+        let mut in_scope = 0;
+        for (pattern, part, var_type) in self.parts(value).iter() {
+            in_scope += match pattern {
+                FunctionParameter::Variable(v) => {
+                    let index = locals.declare(v.name, var_type.clone());
+                    sets.push(wasm!("local.set", index, wasm::Expression::new(part)));
+                    1
+                }
+                _ => pattern.scope(part, locals, sets),
+            };
+        }
+        in_scope
+    }
+}
+
+impl<'a> ConsPattern<'a> {
+    fn head_wat(&self, value: &str) -> String {
+        let element_type = checked_type(&self.element_type);
+        head(&element_type, wasm::Expression::new(value)).to_string()
+    }
+
+    fn tail_wat(&self, value: &str) -> String {
+        tail(wasm::Expression::new(value)).to_string()
+    }
+}
+
+impl<'a> ConstructorPattern<'a> {
+    fn checked_constructor(&self) -> Constructor<'a> {
+        self.constructor
+            .borrow()
+            .clone()
+            .expect("pattern was lowered before it was type checked")
+    }
+
+    fn field_wat(&self, value: &str, index: usize) -> String {
+        let types = self.checked_constructor().cell_types();
+        field(&types, index, wasm::Expression::new(value)).to_string()
+    }
+}
+
+impl<'a> TuplePattern<'a> {
+    fn field_wat(&self, value: &str, index: usize) -> String {
+        match checked_type(&self.tuple_type) {
+            VariableType::Tuple(types) => {
+                field(&types, index, wasm::Expression::new(value)).to_string()
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+// a type as written in a signature, such as `int`, `[[char]]`, `(int, [bool])` or `Shape`
+fn str_into_variable_type(s: &str) -> VariableType {
+    match s.trim() {
         "bool" => VariableType::Bool,
+        "char" => VariableType::Char,
+        "double" => VariableType::Double,
         "float" => VariableType::Float,
         "int" => VariableType::Int,
+        "int64" => VariableType::Int64,
+        "string" => VariableType::String,
+        "unit" => VariableType::Unit,
+        s if s.starts_with('[') => {
+            VariableType::List(Box::new(str_into_variable_type(&s[1..s.len() - 1])))
+        }
+        s if s.starts_with(char::is_uppercase) => VariableType::Data(s.to_string()),
+        s if s.starts_with('(') => {
+            // the elements are separated by the commas that aren't inside another type
+            let mut elements = vec![];
+            let (mut depth, mut start) = (0, 1);
+            for (i, c) in s.char_indices() {
+                match c {
+                    '(' | '[' => depth += 1,
+                    ')' | ']' => depth -= 1,
+                    ',' if depth == 1 => {
+                        elements.push(str_into_variable_type(&s[start..i]));
+                        start = i + 1;
+                    }
+                    _ => {}
+                }
+            }
+            elements.push(str_into_variable_type(&s[start..s.len() - 1]));
+            VariableType::Tuple(elements)
+        }
         _ => unreachable!(),
     }
 }
 
+fn span_into_variable_type(span: Span) -> VariableType {
+    str_into_variable_type(span.as_str())
+}
+
 #[derive(Debug, FromPest)]
 #[pest_ast(rule(Rule::function_signature))]
 pub struct AstFunctionSignature<'a> {
     pub name: VariableName<'a>,
-    pub types: Vec<VarType>,
+    pub types: Vec<VarType<'a>>,
 }
 
 #[derive(Debug, FromPest)]
 #[pest_ast(rule(Rule::var_type))]
-pub struct VarType {
+pub struct VarType<'a> {
     #[pest_ast(inner(with(span_into_variable_type)))]
     pub var_type: VariableType,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
 }
 
 #[derive(Debug)]
@@ -57,12 +409,27 @@ impl std::fmt::Display for FunctionSignature {
 pub struct Function<'a> {
     pub name: VariableName<'a>,
     pub parameters: Vec<FunctionParameter<'a>>,
+    pub guard: Option<Guard<'a>>,
+    pub expr: Expression<'a>,
+    pub where_clause: Option<Where<'a>>,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+    // the type of the expression, known once the function has been validated
+    #[pest_ast(outer(with(span_into_default)))]
+    pub return_type: RefCell<Option<VariableType>>,
+}
+
+// `| condition`, a case of a function that only applies when the condition holds
+#[derive(Debug, FromPest)]
+#[pest_ast(rule(Rule::guard))]
+pub struct Guard<'a> {
     pub expr: Expression<'a>,
 }
 
 #[derive(Debug, FromPest)]
 #[pest_ast(rule(Rule::line))]
 pub enum Line<'a> {
+    Data(DataDeclaration<'a>),
     FunctionSignature(AstFunctionSignature<'a>),
     Function(Function<'a>),
 }
@@ -70,124 +437,235 @@ pub enum Line<'a> {
 impl<'a> Function<'a> {
     pub fn validate(
         &self,
-        current_function: &'a str,
-        globals: &HashMap<&'a str, Vec<&Function<'a>>>,
-        signatures: &mut HashMap<&'a str, FunctionSignature>,
-        validated: &mut HashSet<&'a str>,
-        arg_types: &Vec<VariableType>,
-    ) -> Result<VariableType, Box<dyn std::error::Error>> {
-        if current_function == self.name.name && !signatures.contains_key(self.name.name) {
-            return Err(Box::new(UntypedFunctionError {
-                function_name: self.name.name.to_string(),
-            }));
-        }
-
+        call_site: &Span<'a>,
+        ctx: &mut Context<'a, '_>,
+        arg_types: &[VariableType],
+    ) -> Result<VariableType, Box<dyn CompileError>> {
         if arg_types.len() != self.parameters.len() {
-            return Err(Box::new(crate::err::ArgumentError {
+            return Err(Box::new(ArgumentError {
                 function_name: self.name.name.to_string(),
                 expected: self.parameters.len(),
                 actual: arg_types.len(),
+                location: Location::new(call_site),
             }));
         }
 
         for i in 0..self.parameters.len() {
-            match &self.parameters[i] {
-                FunctionParameter::Variable(_) => {}
-                FunctionParameter::Literal(l) => {
-                    if l.get_type() != arg_types[i] {
-                        return Err(Box::new(TypeMismatchError {
-                            expected: l.get_type(),
-                            got: arg_types[i],
-                        }));
-                    }
-                }
-            };
-        }
-
-        let mut local_types = HashMap::<&str, VariableType>::new();
-        for i in 0..self.parameters.len() {
-            match &self.parameters[i] {
-                FunctionParameter::Variable(v) => {
-                    local_types.insert(v.name, arg_types[i]);
+            if let FunctionParameter::Literal(l) = &self.parameters[i] {
+                if l.get_type() != arg_types[i] {
+                    return Err(Box::new(TypeMismatchError {
+                        expected: l.get_type(),
+                        got: arg_types[i].clone(),
+                        location: Location::new(call_site),
+                    }));
                 }
-                FunctionParameter::Literal(_) => {}
-            };
+            }
         }
 
-        if validated.contains(self.name.name) {
-            let signature = signatures.get(self.name.name).unwrap();
+        if let Some(signature) = ctx.signatures.get(self.name.name) {
             if arg_types.len() != signature.arg_types.len() {
                 return Err(Box::new(ArgumentError {
                     function_name: self.name.name.to_string(),
                     expected: signature.arg_types.len(),
                     actual: arg_types.len(),
+                    location: Location::new(call_site),
                 }));
             }
             for i in 0..arg_types.len() {
-                if arg_types[i] != signature.arg_types[i] {
-                    return Err(Box::new(TypeMismatchError {
-                        expected: signature.arg_types[i],
-                        got: arg_types[i],
-                    }));
+                if !signature.arg_types[i].accepts(&arg_types[i]) {
+                    return Err(type_mismatch(
+                        signature.arg_types[i].clone(),
+                        arg_types[i].clone(),
+                        Location::new(call_site),
+                    ));
                 }
             }
-            return Ok(signature.return_type);
         }
 
-        validated.insert(self.name.name);
-        Ok(match signatures.get(self.name.name) {
-            Some(sig) => sig.return_type,
-            None => {
-                let return_type = self.expr.validate(
-                    self.name.name,
-                    globals,
-                    signatures,
-                    validated,
-                    &local_types,
-                )?;
-                let signature = FunctionSignature {
-                    arg_types: arg_types.to_vec(),
-                    return_type: return_type,
-                };
-                signatures.insert(self.name.name, signature);
-                return_type
+        if ctx.failed.contains(self.name.name) {
+            return Err(Box::new(ReportedError));
+        }
+
+        if ctx.validated.contains(self.name.name) {
+            return match ctx.signatures.get(self.name.name) {
+                Some(signature) => Ok(signature.return_type.clone()),
+                // still working out the return type further up, so this is a recursive call
+                None => Err(Box::new(UntypedFunctionError {
+                    function_name: self.name.name.to_string(),
+                    location: Location::new(call_site),
+                })),
+            };
+        }
+
+        ctx.validated.insert(self.name.name);
+        let declared = ctx.signatures.contains_key(self.name.name);
+        // a declared function is checked against its signature rather than the first call, as
+        // `[]` can be passed for a list of anything
+        let (arg_types, return_type) = match ctx.signatures.get(self.name.name) {
+            Some(sig) => (sig.arg_types.clone(), sig.return_type.clone()),
+            None => match self.validate_body(ctx, arg_types) {
+                Ok(return_type) => {
+                    let signature = FunctionSignature {
+                        arg_types: arg_types.to_vec(),
+                        return_type: return_type.clone(),
+                    };
+                    ctx.signatures.insert(self.name.name, signature);
+                    (arg_types.to_vec(), return_type)
+                }
+                Err(e) => {
+                    ctx.failed.insert(self.name.name);
+                    ctx.diagnostics.error(e);
+                    return Err(Box::new(ReportedError));
+                }
+            },
+        };
+
+        // with the signature known, every other case of the function can be checked against it
+        let functions = ctx.functions;
+        for f in functions.get(self.name.name).unwrap() {
+            if std::ptr::eq(*f, self) && !declared {
+                continue;
             }
-        })
+            match f.validate_body(ctx, &arg_types) {
+                // the first call only passed `[]`, so the type of its elements is still unknown
+                Ok(t) if !return_type.accepts(&t) && return_type.unify(&t).is_some() => {
+                    ctx.diagnostics.error(Box::new(UntypedFunctionError {
+                        function_name: self.name.name.to_string(),
+                        location: Location::new(f.expr.span()),
+                    }))
+                }
+                Ok(t) if !return_type.accepts(&t) => ctx.diagnostics.error(type_mismatch(
+                    return_type.clone(),
+                    t,
+                    Location::new(f.expr.span()),
+                )),
+                Ok(_) => {}
+                Err(e) => ctx.diagnostics.error(e),
+            }
+        }
+        check_cases(
+            self.name.name,
+            functions.get(self.name.name).unwrap(),
+            &arg_types,
+            &ctx.data_types,
+            ctx.diagnostics,
+        );
+
+        Ok(return_type)
     }
 
-    pub fn to_wasm(&self, return_type: VariableType) -> wasm::Expression {
-        let mut locals_to_arg_index = HashMap::<&str, usize>::new();
-        for i in 0..self.parameters.len() {
-            match &self.parameters[i] {
+    fn validate_body(
+        &self,
+        ctx: &mut Context<'a, '_>,
+        arg_types: &[VariableType],
+    ) -> Result<VariableType, Box<dyn CompileError>> {
+        let mut local_types = HashMap::<&str, VariableType>::new();
+        for (param, arg_type) in self.parameters.iter().zip(arg_types) {
+            param.bind(arg_type, &ctx.data_types, &mut local_types)?;
+        }
+
+        // the guard is checked before the where bindings are set, so it only sees the parameters
+        if let Some(guard) = &self.guard {
+            match guard.expr.validate(ctx, &local_types) {
+                Ok(VariableType::Bool) => {}
+                Ok(t) => ctx.diagnostics.error(Box::new(TypeMismatchError {
+                    expected: VariableType::Bool,
+                    got: t,
+                    location: Location::new(guard.expr.span()),
+                })),
+                Err(e) => ctx.diagnostics.error(e),
+            }
+        }
+
+        if let Some(where_clause) = &self.where_clause {
+            local_types = validate_bindings(&where_clause.bindings, ctx, &local_types)?;
+        }
+
+        let return_type = self.expr.validate(ctx, &local_types)?;
+        *self.return_type.borrow_mut() = Some(return_type.clone());
+        Ok(return_type)
+    }
+
+    // Brings the named parameters into scope, returning how many there are to release and the
+    // instructions loading the ones inside lists into locals.
+    fn scope_parameters(&self, locals: &mut Locals<'a>) -> (usize, Vec<wasm::Expression>) {
+        let mut in_scope = 0;
+        let mut sets = vec![];
+        for (i, param) in self.parameters.iter().enumerate() {
+            match param {
                 FunctionParameter::Variable(v) => {
-                    locals_to_arg_index.insert(v.name, i);
+                    locals.parameter(v.name, i);
+                    in_scope += 1;
                 }
-                FunctionParameter::Literal(_) => {}
+                _ => in_scope += param.scope(&format!("(local.get {})", i), locals, &mut sets),
             };
         }
+        (in_scope, sets)
+    }
+
+    pub fn to_wasm(&self, locals: &mut Locals<'a>) -> wasm::Expression {
+        let (in_scope, sets) = self.scope_parameters(locals);
+
+        let expr = match (&self.where_clause, sets.is_empty()) {
+            (None, true) => self.expr.to_wasm(locals),
+            (where_clause, _) => {
+                let mut block = vec![
+                    wasm!("block"),
+                    wasm!("result", checked_type(&self.return_type).to_wasm()),
+                ];
+                block.extend(sets);
+                if let Some(where_clause) = where_clause {
+                    block.extend(bindings_to_wasm(&where_clause.bindings, locals));
+                }
+                block.push(self.expr.to_wasm(locals));
+                if let Some(where_clause) = where_clause {
+                    locals.release(where_clause.bindings.len());
+                }
+                wasm!(block)
+            }
+        };
+
+        locals.release(in_scope);
+        expr
+    }
+
+    // The condition for this case to apply, or None when it matches everything. The guard is
+    // only evaluated once the patterns of the parameters have matched.
+    pub fn matches_condition(&self, locals: &mut Locals<'a>) -> Option<wasm::Expression> {
+        let literals = self.wat_matches_condition().map(wasm::Expression::new);
+        let guard = self.guard.as_ref().map(|guard| {
+            let (in_scope, sets) = self.scope_parameters(locals);
+            let expr = guard.expr.to_wasm(locals);
+            locals.release(in_scope);
+            match sets.is_empty() {
+                true => expr,
+                false => {
+                    let mut block = vec![wasm!("block"), wasm!("result", "i32")];
+                    block.extend(sets);
+                    block.push(expr);
+                    wasm!(block)
+                }
+            }
+        });
 
-        self.expr.to_wasm(return_type, &locals_to_arg_index)
+        match (literals, guard) {
+            (Some(literals), Some(guard)) => Some(wasm!(
+                "if",
+                wasm!("result", "i32"),
+                literals,
+                wasm!("then", guard),
+                wasm!("else", wasm!("i32.const", 0))
+            )),
+            (literals, None) => literals,
+            (None, guard) => guard,
+        }
     }
 
     pub fn wat_matches_condition(&self) -> Option<String> {
         let mut conditions = Vec::<String>::new();
 
         for (i, param) in self.parameters.iter().enumerate() {
-            match param {
-                FunctionParameter::Variable(_) => {}
-                FunctionParameter::Literal(l) => {
-                    conditions.push(format!(
-                        r#"({}.eq
-    (get_local {})
-    ({}.const {})
-)"#,
-                        l.get_type().to_wasm(),
-                        i,
-                        l.get_type().to_wasm(),
-                        l,
-                    ));
-                }
-            }
+            param.wat_conditions(&format!("(get_local {})", i), &mut conditions);
         }
 
         if conditions.len() == 0 {
@@ -197,6 +675,27 @@ impl<'a> Function<'a> {
     }
 }
 
+// `first` and then, only if it holds, the rest of the conditions
+fn wat_and_then(first: String, rest: &mut Vec<String>) -> String {
+    if rest.is_empty() {
+        return first;
+    }
+    format!(
+        r#"(if (result i32)
+    {}
+    (then
+        {}
+    )
+    (else (i32.const 0))
+)"#,
+        first.split('\n').collect::<Vec<&str>>().join("\n    "),
+        combine_wat_conditions(rest)
+            .split('\n')
+            .collect::<Vec<&str>>()
+            .join("\n        "),
+    )
+}
+
 pub fn combine_wat_conditions(conditions: &mut Vec<String>) -> String {
     let left = conditions.pop().unwrap();
     let right = match conditions.len() {
@@ -0,0 +1,77 @@
+use crate::ast::context::Context;
+use crate::ast::expression::Expression;
+use crate::ast::locals::Locals;
+use crate::ast::util::{checked_type, span_into_default, validate_all};
+use crate::ast::variable::VariableType;
+use crate::err::{CompileError, ListElementError, Location};
+use crate::parser::Rule;
+use crate::stdlib::cons;
+use crate::wasm;
+use pest::Span;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+// `[1, 2, 3]`, built up from the last element as if it was written `1 : 2 : 3 : []`
+#[derive(Debug, FromPest)]
+#[pest_ast(rule(Rule::list))]
+pub struct List<'a> {
+    pub elements: Vec<Expression<'a>>,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+    // the type of the elements, known once the list has been validated
+    #[pest_ast(outer(with(span_into_default)))]
+    pub element_type: RefCell<Option<VariableType>>,
+}
+
+impl<'a> List<'a> {
+    pub fn validate(
+        &self,
+        ctx: &mut Context<'a, '_>,
+        local_types: &HashMap<&'a str, VariableType>,
+    ) -> Result<VariableType, Box<dyn CompileError>> {
+        let results = self
+            .elements
+            .iter()
+            .map(|e| e.validate(ctx, local_types))
+            .collect();
+        let types = validate_all(results, ctx.diagnostics)?;
+
+        let mut element_type = VariableType::Unknown;
+        for (element, t) in self.elements.iter().zip(types) {
+            element_type = match element_type.unify(&t) {
+                Some(unified) => unified,
+                None => {
+                    return Err(Box::new(ListElementError {
+                        expected: element_type,
+                        got: t,
+                        location: Location::new(element.span()),
+                    }))
+                }
+            };
+        }
+
+        *self.element_type.borrow_mut() = Some(element_type.clone());
+        Ok(VariableType::List(Box::new(element_type)))
+    }
+
+    pub fn to_wasm(&self, locals: &mut Locals<'a>) -> wasm::Expression {
+        let element_type = checked_type(&self.element_type);
+        let last = self.elements.len().saturating_sub(1);
+
+        // every element but the last is kept while the ones after it are evaluated
+        let mut elements = vec![];
+        for (i, element) in self.elements.iter().enumerate() {
+            let value = element.to_wasm(locals);
+            elements.push(match i < last {
+                true => locals.root(&element_type, value),
+                false => value,
+            });
+        }
+
+        let mut list = wasm!("i32.const", 0);
+        while let Some(element) = elements.pop() {
+            list = cons(&element_type, element, list);
+        }
+        list
+    }
+}
@@ -0,0 +1,86 @@
+use crate::ast::variable::VariableType;
+use crate::stdlib::keep;
+use crate::wasm;
+use std::collections::HashMap;
+
+// The wasm locals of a function while its body is lowered. Parameters come first, followed by a
+// local for every binding, and each name maps to the innermost local it is bound to.
+pub struct Locals<'a> {
+    parameters: usize,
+    scope: HashMap<&'a str, usize>,
+    shadowed: Vec<(&'a str, Option<usize>)>,
+    pub declarations: Vec<VariableType>,
+    // Heap addresses the function holds on to while it allocates more are kept in a frame on the
+    // shadow stack, so the collector can find them. The frame is only set up once it is needed.
+    frame: Option<usize>,
+    slots: usize,
+}
+
+impl<'a> Locals<'a> {
+    pub fn new(parameters: usize) -> Self {
+        Self {
+            parameters,
+            scope: HashMap::new(),
+            shadowed: vec![],
+            declarations: vec![],
+            frame: None,
+            slots: 0,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.scope.get(name).copied()
+    }
+
+    // bring a parameter into scope
+    pub fn parameter(&mut self, name: &'a str, index: usize) {
+        self.shadowed.push((name, self.scope.insert(name, index)));
+    }
+
+    // declare a new local and bring it into scope, returning its index
+    pub fn declare(&mut self, name: &'a str, var_type: VariableType) -> usize {
+        let index = self.temporary(var_type);
+        self.shadowed.push((name, self.scope.insert(name, index)));
+        index
+    }
+
+    // declare a new local that no name refers to, returning its index
+    pub fn temporary(&mut self, var_type: VariableType) -> usize {
+        self.declarations.push(var_type);
+        self.parameters + self.declarations.len() - 1
+    }
+
+    // Keeps the value in a slot of its own in the frame when it is a heap address, so it
+    // survives whatever is allocated before the function is done with it.
+    pub fn root(&mut self, var_type: &VariableType, value: wasm::Expression) -> wasm::Expression {
+        if !var_type.is_heap() {
+            return value;
+        }
+        let frame = match self.frame {
+            Some(frame) => frame,
+            None => {
+                let frame = self.temporary(VariableType::Int);
+                self.frame = Some(frame);
+                frame
+            }
+        };
+        self.slots += 1;
+        keep(wasm!("local.get", frame), self.slots - 1, value)
+    }
+
+    // the local holding the address of the frame and how many slots it needs, if it has one
+    pub fn frame(&self) -> Option<(usize, usize)> {
+        self.frame.map(|frame| (frame, self.slots))
+    }
+
+    // take the last `count` names out of scope, uncovering anything they shadowed
+    pub fn release(&mut self, count: usize) {
+        for _ in 0..count {
+            let (name, previous) = self.shadowed.pop().unwrap();
+            match previous {
+                Some(index) => self.scope.insert(name, index),
+                None => self.scope.remove(name),
+            };
+        }
+    }
+}
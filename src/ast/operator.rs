@@ -1,8 +1,16 @@
 use super::util::span_into_phantomdata;
+use crate::ast::variable::VariableType;
 use crate::parser::Rule;
 use crate::{wasm, wasm::Expression};
 use std::marker::PhantomData;
 
+#[derive(Debug, FromPest, Copy, Clone)]
+#[pest_ast(rule(Rule::cons))]
+pub struct Cons {
+    #[pest_ast(outer(with(span_into_phantomdata)))]
+    n: PhantomData<()>,
+}
+
 #[derive(Debug, FromPest, Copy, Clone)]
 #[pest_ast(rule(Rule::add))]
 pub struct Add {
@@ -45,26 +53,166 @@ pub struct Neq {
     n: PhantomData<()>,
 }
 
+#[derive(Debug, FromPest, Copy, Clone)]
+#[pest_ast(rule(Rule::lt))]
+pub struct Lt {
+    #[pest_ast(outer(with(span_into_phantomdata)))]
+    n: PhantomData<()>,
+}
+
+#[derive(Debug, FromPest, Copy, Clone)]
+#[pest_ast(rule(Rule::lte))]
+pub struct Lte {
+    #[pest_ast(outer(with(span_into_phantomdata)))]
+    n: PhantomData<()>,
+}
+
+#[derive(Debug, FromPest, Copy, Clone)]
+#[pest_ast(rule(Rule::gt))]
+pub struct Gt {
+    #[pest_ast(outer(with(span_into_phantomdata)))]
+    n: PhantomData<()>,
+}
+
+#[derive(Debug, FromPest, Copy, Clone)]
+#[pest_ast(rule(Rule::gte))]
+pub struct Gte {
+    #[pest_ast(outer(with(span_into_phantomdata)))]
+    n: PhantomData<()>,
+}
+
+#[derive(Debug, FromPest, Copy, Clone)]
+#[pest_ast(rule(Rule::and))]
+pub struct And {
+    #[pest_ast(outer(with(span_into_phantomdata)))]
+    n: PhantomData<()>,
+}
+
+#[derive(Debug, FromPest, Copy, Clone)]
+#[pest_ast(rule(Rule::or))]
+pub struct Or {
+    #[pest_ast(outer(with(span_into_phantomdata)))]
+    n: PhantomData<()>,
+}
+
+#[derive(Debug, FromPest, Copy, Clone)]
+#[pest_ast(rule(Rule::modulo))]
+pub struct Modulo {
+    #[pest_ast(outer(with(span_into_phantomdata)))]
+    n: PhantomData<()>,
+}
+
+#[derive(Debug, FromPest, Copy, Clone)]
+#[pest_ast(rule(Rule::bit_and))]
+pub struct BitAnd {
+    #[pest_ast(outer(with(span_into_phantomdata)))]
+    n: PhantomData<()>,
+}
+
+#[derive(Debug, FromPest, Copy, Clone)]
+#[pest_ast(rule(Rule::bit_or))]
+pub struct BitOr {
+    #[pest_ast(outer(with(span_into_phantomdata)))]
+    n: PhantomData<()>,
+}
+
+#[derive(Debug, FromPest, Copy, Clone)]
+#[pest_ast(rule(Rule::bit_xor))]
+pub struct BitXor {
+    #[pest_ast(outer(with(span_into_phantomdata)))]
+    n: PhantomData<()>,
+}
+
+#[derive(Debug, FromPest, Copy, Clone)]
+#[pest_ast(rule(Rule::shift_left))]
+pub struct ShiftLeft {
+    #[pest_ast(outer(with(span_into_phantomdata)))]
+    n: PhantomData<()>,
+}
+
+#[derive(Debug, FromPest, Copy, Clone)]
+#[pest_ast(rule(Rule::shift_right))]
+pub struct ShiftRight {
+    #[pest_ast(outer(with(span_into_phantomdata)))]
+    n: PhantomData<()>,
+}
+
 #[derive(Debug, FromPest, Copy, Clone)]
 #[pest_ast(rule(Rule::operator))]
 pub enum Operator {
+    Cons(Cons),
     Add(Add),
     Subtract(Subtract),
     Multiply(Multiply),
     Divide(Divide),
     Eq(Eq),
     Neq(Neq),
+    Lt(Lt),
+    Lte(Lte),
+    Gt(Gt),
+    Gte(Gte),
+    And(And),
+    Or(Or),
+    Modulo(Modulo),
+    BitAnd(BitAnd),
+    BitOr(BitOr),
+    BitXor(BitXor),
+    ShiftLeft(ShiftLeft),
+    ShiftRight(ShiftRight),
 }
 
 impl Operator {
-    pub fn to_wasm(&self) -> Expression {
+    // operators with a higher precedence bind tighter
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Operator::Multiply(_) | Operator::Divide(_) | Operator::Modulo(_) => 10,
+            Operator::Add(_) | Operator::Subtract(_) => 9,
+            Operator::ShiftLeft(_) | Operator::ShiftRight(_) => 8,
+            Operator::BitAnd(_) => 7,
+            Operator::BitXor(_) => 6,
+            Operator::BitOr(_) => 5,
+            Operator::Cons(_) => 4,
+            Operator::Eq(_)
+            | Operator::Neq(_)
+            | Operator::Lt(_)
+            | Operator::Lte(_)
+            | Operator::Gt(_)
+            | Operator::Gte(_) => 3,
+            Operator::And(_) => 2,
+            Operator::Or(_) => 1,
+        }
+    }
+
+    // `1 : 2 : []` puts 2 on the empty list first, every other operator is left associative
+    pub fn is_right_associative(&self) -> bool {
+        matches!(self, Operator::Cons(_))
+    }
+
+    // the instruction for the operator, ints are signed so some need a suffix
+    pub fn to_wasm(&self, operand_type: &VariableType) -> Expression {
+        let sign = match operand_type.is_float() {
+            true => "",
+            false => "_s",
+        };
         match self {
             Operator::Add(_) => wasm!("add"),
             Operator::Subtract(_) => wasm!("sub"),
             Operator::Multiply(_) => wasm!("mul"),
-            Operator::Divide(_) => wasm!("div"),
+            Operator::Divide(_) => wasm!(format!("div{}", sign)),
             Operator::Eq(_) => wasm!("eq"),
             Operator::Neq(_) => wasm!("ne"),
+            Operator::Lt(_) => wasm!(format!("lt{}", sign)),
+            Operator::Lte(_) => wasm!(format!("le{}", sign)),
+            Operator::Gt(_) => wasm!(format!("gt{}", sign)),
+            Operator::Gte(_) => wasm!(format!("ge{}", sign)),
+            Operator::And(_) | Operator::BitAnd(_) => wasm!("and"),
+            Operator::Or(_) | Operator::BitOr(_) => wasm!("or"),
+            Operator::Modulo(_) => wasm!(format!("rem{}", sign)),
+            Operator::BitXor(_) => wasm!("xor"),
+            Operator::ShiftLeft(_) => wasm!("shl"),
+            Operator::ShiftRight(_) => wasm!(format!("shr{}", sign)),
+            // builds a cell on the heap rather than being an instruction
+            Operator::Cons(_) => unreachable!(),
         }
     }
 }
@@ -72,12 +220,55 @@ impl Operator {
 impl std::fmt::Display for Operator {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            Operator::Add(_) => write!(f, "add"),
-            Operator::Subtract(_) => write!(f, "sub"),
-            Operator::Multiply(_) => write!(f, "mul"),
-            Operator::Divide(_) => write!(f, "div"),
-            Operator::Eq(_) => write!(f, "eq"),
-            Operator::Neq(_) => write!(f, "ne"),
+            Operator::Cons(_) => write!(f, ":"),
+            Operator::Add(_) => write!(f, "+"),
+            Operator::Subtract(_) => write!(f, "-"),
+            Operator::Multiply(_) => write!(f, "*"),
+            Operator::Divide(_) => write!(f, "/"),
+            Operator::Eq(_) => write!(f, "=="),
+            Operator::Neq(_) => write!(f, "!="),
+            Operator::Lt(_) => write!(f, "<"),
+            Operator::Lte(_) => write!(f, "<="),
+            Operator::Gt(_) => write!(f, ">"),
+            Operator::Gte(_) => write!(f, ">="),
+            Operator::And(_) => write!(f, "&&"),
+            Operator::Or(_) => write!(f, "||"),
+            Operator::Modulo(_) => write!(f, "%"),
+            Operator::BitAnd(_) => write!(f, "&"),
+            Operator::BitOr(_) => write!(f, "|"),
+            Operator::BitXor(_) => write!(f, "^"),
+            Operator::ShiftLeft(_) => write!(f, "<<"),
+            Operator::ShiftRight(_) => write!(f, ">>"),
+        }
+    }
+}
+
+#[derive(Debug, FromPest, Copy, Clone)]
+#[pest_ast(rule(Rule::not))]
+pub struct Not {
+    #[pest_ast(outer(with(span_into_phantomdata)))]
+    n: PhantomData<()>,
+}
+
+#[derive(Debug, FromPest, Copy, Clone)]
+#[pest_ast(rule(Rule::negate))]
+pub struct Negate {
+    #[pest_ast(outer(with(span_into_phantomdata)))]
+    n: PhantomData<()>,
+}
+
+#[derive(Debug, FromPest, Copy, Clone)]
+#[pest_ast(rule(Rule::prefix_operator))]
+pub enum PrefixOperator {
+    Not(Not),
+    Negate(Negate),
+}
+
+impl std::fmt::Display for PrefixOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PrefixOperator::Not(_) => write!(f, "!"),
+            PrefixOperator::Negate(_) => write!(f, "-"),
         }
     }
 }
@@ -1,8 +1,18 @@
+use crate::ast::context::Context;
+use crate::ast::data::Data;
+use crate::ast::data_type::DataTypes;
+use crate::ast::function::VarType;
+use crate::ast::locals::Locals;
+use crate::ast::VariableType;
 use crate::ast::{Function, FunctionSignature, Line};
-use crate::err;
+use crate::err::{self, Diagnostics};
 use crate::parser::Rule;
-use crate::stdlib::Lib;
+use crate::stdlib::{
+    field, head, heap_layout, pop_frame, push_frame, tail, Lib, DATA_START, DOUBLE_SCALE,
+    FLOAT_SCALE,
+};
 use crate::{wasm, wasm::Expression, wasm_dollar, wasm_quote};
+use pest::Span;
 use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, FromPest)]
@@ -10,6 +20,8 @@ use std::collections::{HashMap, HashSet};
 pub struct Program<'a> {
     pub lines: Vec<Line<'a>>,
     _eoi: EOI,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
 }
 
 #[derive(Debug, FromPest)]
@@ -17,10 +29,15 @@ pub struct Program<'a> {
 struct EOI;
 
 impl<'a> Program<'a> {
-    pub fn to_wasm(&self, lib: Lib<'a>) -> Result<Expression, Box<dyn std::error::Error>> {
+    pub fn to_wasm(
+        &self,
+        lib: Lib<'a>,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<Expression, Box<dyn std::error::Error>> {
         let mut functions = HashMap::<&str, Vec<&Function>>::new();
         let mut function_signatures = HashMap::<&str, FunctionSignature>::new();
-        let mut validated = HashSet::<&str>::new();
+        let mut declarations = vec![];
+        let mut data_types = DataTypes::default();
 
         let mut module = wasm!(
             "module",
@@ -35,77 +52,158 @@ impl<'a> Program<'a> {
                     wasm!("result", "i32")
                 )
             ),
-            wasm!("export", wasm_quote!("memory"), wasm!("memory", 0)),
-            wasm!("memory", 1),
-            wasm!(
-                "func",
-                wasm_dollar!("_start"),
-                wasm!("export", wasm_quote!("_start")),
-                wasm!(
-                    "call",
-                    wasm_dollar!("printi"),
-                    wasm!("call", wasm_dollar!("main"))
-                ),
-                wasm!("call", wasm_dollar!("printc"), wasm!("i32.const", 10))
-            )
+            wasm!("export", wasm_quote!("memory"), wasm!("memory", 0))
         );
 
+        for helper in lib.helpers {
+            module = module.extend(helper);
+        }
+
         for (name, func) in lib.funcs {
             function_signatures.insert(name, func.sig);
             functions.insert(name, vec![]);
             module = module.extend(func.wasm);
         }
 
+        // data types come first, so signatures can refer to types declared below them
+        for l in self.lines.iter() {
+            if let Line::Data(d) = l {
+                data_types.declare(d, diagnostics);
+            }
+        }
+
         for l in self.lines.iter() {
             match l {
+                Line::Data(d) => {
+                    for c in &d.constructors {
+                        for t in &c.types {
+                            undeclared_type(&data_types, t, diagnostics);
+                        }
+                    }
+                }
                 Line::Function(f) => match functions.get_mut(f.name.name) {
                     Some(fns) => fns.push(f),
                     None => {
+                        if let Err(e) = f.name.check_lowercase() {
+                            diagnostics.error(e);
+                        }
                         functions.insert(f.name.name, vec![f]);
                     }
                 },
                 Line::FunctionSignature(s) => {
+                    for t in &s.types {
+                        undeclared_type(&data_types, t, diagnostics);
+                    }
+                    declarations.push(s);
                     function_signatures.insert(
                         s.name.name,
                         FunctionSignature {
                             arg_types: s.types[0..s.types.len() - 1]
                                 .iter()
-                                .map(|x| x.var_type)
+                                .map(|x| x.var_type.clone())
                                 .collect(),
-                            return_type: s.types.last().unwrap().var_type,
+                            return_type: s.types.last().unwrap().var_type.clone(),
                         },
                     );
                 }
             }
         }
 
-        let main = match functions.get("main") {
-            Some(f) => &f[0],
+        // constructors are called like library functions, with nothing to check but their
+        // signature
+        for (&name, constructor) in &data_types.constructors {
+            if functions.contains_key(name) {
+                diagnostics.error(Box::new(err::FunctionAlreadyDefinedError {
+                    function_name: name.to_string(),
+                    location: err::Location::new(&constructor.span),
+                }));
+                continue;
+            }
+            functions.insert(name, vec![]);
+            function_signatures.insert(name, constructor.signature());
+        }
+
+        let mut ctx = Context {
+            functions: &functions,
+            signatures: function_signatures,
+            validated: HashSet::new(),
+            failed: HashSet::new(),
+            referenced: HashSet::new(),
+            diagnostics,
+            data: Data::new(DATA_START),
+            data_types,
+        };
+
+        match functions.get("main") {
+            Some(main) => {
+                if let Err(e) = main[0].validate(&main[0].name.span, &mut ctx, &[]) {
+                    ctx.diagnostics.error(e);
+                }
+            }
             None => {
-                return Err(Box::new(err::FunctionNotFoundError {
+                let start = self.span.start_pos();
+                ctx.diagnostics.error(Box::new(err::FunctionNotFoundError {
                     name: "main".to_string(),
+                    location: err::Location::new(&start.span(&start)),
                 }));
             }
         };
 
-        main.validate(
-            "",
-            &functions,
-            &mut function_signatures,
-            &mut validated,
-            &vec![],
-        )?;
-        for (fname, _) in &functions {
-            match function_signatures.get(fname) {
-                None => eprintln!("Warning: unused function {}", fname),
-                _ => {}
+        // functions with a type signature can be checked even if main never calls them
+        for declaration in declarations {
+            let name = declaration.name.name;
+            match functions.get(name) {
+                Some(fns) if !fns.is_empty() => {
+                    let arg_types = ctx.signatures.get(name).unwrap().arg_types.to_vec();
+                    if let Err(e) = fns[0].validate(&fns[0].name.span, &mut ctx, &arg_types) {
+                        ctx.diagnostics.error(e);
+                    }
+                }
+                _ => ctx.diagnostics.error(Box::new(err::FunctionNotFoundError {
+                    name: name.to_string(),
+                    location: err::Location::new(&declaration.name.span),
+                })),
+            }
+        }
+
+        for (fname, fns) in &functions {
+            if !fns.is_empty() && !ctx.validated.contains(fname) && !ctx.referenced.contains(fname)
+            {
+                ctx.diagnostics
+                    .warning(Box::new(err::UnusedFunctionWarning {
+                        function_name: fname.to_string(),
+                        location: err::Location::new(&fns[0].name.span),
+                    }));
             }
         }
 
+        if ctx.diagnostics.has_errors() {
+            return Err(Box::new(err::StandardError {
+                s: ctx.diagnostics.summary(),
+            }));
+        }
+
+        for (name, constructor) in &ctx.data_types.constructors {
+            module = module.extend(constructor.to_wasm(name));
+        }
+        for type_name in ctx.data_types.types.keys() {
+            module = module.extend(write_data(type_name, &ctx.data_types));
+        }
+        module = module.extend(start(&ctx.signatures["main"]));
+        for segment in ctx.data.to_wasm() {
+            module = module.extend(segment);
+        }
+        for declaration in heap_layout(ctx.data.end()) {
+            module = module.extend(declaration);
+        }
+
+        let function_signatures = ctx.signatures;
         for (fname, sig) in &function_signatures {
+            // every case of the function shares the same wasm locals
+            let mut locals = Locals::new(sig.arg_types.len());
             let mut fns = vec![];
             for f in functions.get_mut(fname).unwrap() {
-                let cond = f.wat_matches_condition();
+                let cond = f.matches_condition(&mut locals);
                 if cond.is_none() {
                     fns.push((cond, f));
                     break;
@@ -117,22 +215,18 @@ impl<'a> Program<'a> {
                 continue;
             }
 
-            if fns[fns.len() - 1].0.is_some() {
-                return Err(Box::new(err::StandardError {
-                    s: "murulang was unable to ensure the function matches all cases".to_string(),
-                }));
-            }
-
-            let mut inner = fns.pop().unwrap().1.to_wasm(sig.return_type);
+            // the cases are known to cover every input, so whatever reaches the last one matches it
+            let mut inner = fns.pop().unwrap().1.to_wasm(&mut locals);
             while let Some((cond, f)) = fns.pop() {
                 inner = wasm!(
                     "if",
                     wasm!("result", sig.return_type.to_wasm()),
                     cond.unwrap(),
-                    wasm!("then", f.to_wasm(sig.return_type)),
+                    wasm!("then", f.to_wasm(&mut locals)),
                     wasm!("else", inner)
                 );
             }
+            let body = frame(sig, inner, &mut locals);
 
             let mut func = wasm!(wasm!("func"), wasm_dollar!(fname));
 
@@ -144,9 +238,19 @@ impl<'a> Program<'a> {
                 func = func.extend(param);
             }
 
-            func = func
-                .extend(wasm!("result", sig.return_type.to_wasm()))
-                .extend(inner);
+            func = func.extend(wasm!("result", sig.return_type.to_wasm()));
+
+            if !locals.declarations.is_empty() {
+                let mut local = wasm!("local");
+                for ty in locals.declarations.iter() {
+                    local = local.extend(ty.to_wasm());
+                }
+                func = func.extend(local);
+            }
+
+            for instruction in body {
+                func = func.extend(instruction);
+            }
 
             module = module.extend(func);
         }
@@ -155,6 +259,221 @@ impl<'a> Program<'a> {
     }
 }
 
+fn undeclared_type(data_types: &DataTypes, t: &VarType, diagnostics: &mut Diagnostics) {
+    if let Some(name) = data_types.undeclared(&t.var_type) {
+        diagnostics.error(Box::new(err::TypeNotFoundError {
+            name,
+            location: err::Location::new(&t.span),
+        }));
+    }
+}
+
+// The instructions for the body of a function. Functions holding on to heap addresses set up a
+// frame on the shadow stack for them, and take it down again before they return.
+fn frame(sig: &FunctionSignature, body: Expression, locals: &mut Locals) -> Vec<Expression> {
+    let mut parameters = vec![];
+    for (i, arg_type) in sig.arg_types.iter().enumerate() {
+        if arg_type.is_heap() {
+            parameters.push(wasm!("drop", locals.root(arg_type, wasm!("local.get", i))));
+        }
+    }
+
+    let (frame, slots) = match locals.frame() {
+        Some(frame) => frame,
+        None => return vec![body],
+    };
+    let result = locals.temporary(sig.return_type.clone());
+    let mut instructions = vec![wasm!("local.set", frame, push_frame(slots))];
+    instructions.extend(parameters);
+    instructions.extend(vec![
+        wasm!("local.set", result, body),
+        pop_frame(wasm!("local.get", frame)),
+        wasm!("local.get", result),
+    ]);
+    instructions
+}
+
+// Runs main and writes what it returns to stdout, followed by a new line unless it is unit
+fn start(main: &FunctionSignature) -> Expression {
+    let mut func = vec![
+        wasm!("func"),
+        wasm_dollar!("_start"),
+        wasm!("export", wasm_quote!("_start")),
+    ];
+    for depth in 0..heap_depth(&main.return_type) {
+        func.push(wasm!(
+            "local",
+            wasm_dollar!(&format!("heap{}", depth)),
+            "i32"
+        ));
+    }
+    if main.return_type == VariableType::Unit {
+        func.push(wasm!("drop", wasm!("call", wasm_dollar!("main"))));
+        return wasm!(func);
+    }
+
+    func.extend(write(
+        &main.return_type,
+        wasm!("call", wasm_dollar!("main")),
+        0,
+    ));
+    func.push(putc('\n'));
+    wasm!(func)
+}
+
+// how many lists and tuples deep the values of the type go
+fn heap_depth(var_type: &VariableType) -> usize {
+    match var_type {
+        VariableType::List(element_type) => 1 + heap_depth(element_type),
+        VariableType::Tuple(types) => 1 + types.iter().map(heap_depth).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn putc(c: char) -> Expression {
+    wasm!("call", wasm_dollar!("_putc"), wasm!("i32.const", c as i32))
+}
+
+// Writes a value to stdout. Lists are written as `[1, 2, 3]` and tuples as `(1, true)`, with a
+// local holding the address of each list or tuple they are nested in.
+fn write(var_type: &VariableType, value: Expression, depth: usize) -> Vec<Expression> {
+    let write = match var_type {
+        VariableType::Bool => wasm!("call", wasm_dollar!("_putb"), value),
+        VariableType::Char => wasm!("call", wasm_dollar!("_putc"), value),
+        VariableType::Data(name) => wasm!(
+            "call",
+            wasm_dollar!(&format!("_write_{}", name)),
+            value,
+            wasm!("i32.const", 0)
+        ),
+        VariableType::Double => wasm!(
+            "call",
+            wasm_dollar!("_putd"),
+            value,
+            wasm!("f64.const", DOUBLE_SCALE)
+        ),
+        VariableType::Float => wasm!(
+            "call",
+            wasm_dollar!("_putd"),
+            wasm!("f64.promote_f32", value),
+            wasm!("f64.const", FLOAT_SCALE)
+        ),
+        VariableType::Int => wasm!("call", wasm_dollar!("_puti"), value),
+        VariableType::Int64 => wasm!("call", wasm_dollar!("_putl"), value),
+        VariableType::String => wasm!("drop", wasm!("call", wasm_dollar!("prints"), value)),
+        VariableType::Unit => wasm!("drop", value),
+        // the elements of a list that is always empty are never written
+        VariableType::Unknown => wasm!("drop", value),
+        VariableType::List(element_type) => {
+            let list = || wasm!("local.get", wasm_dollar!(&format!("heap{}", depth)));
+            let mut body = vec![wasm!("loop")];
+            body.push(wasm!("br_if", 1, wasm!("i32.eqz", list())));
+            body.extend(write(element_type, head(element_type, list()), depth + 1));
+            body.push(wasm!(
+                "local.set",
+                wasm_dollar!(&format!("heap{}", depth)),
+                tail(list())
+            ));
+            body.push(wasm!("if", list(), wasm!("then", putc(','), putc(' '))));
+            body.push(wasm!("br", 0));
+
+            return vec![
+                wasm!("local.set", wasm_dollar!(&format!("heap{}", depth)), value),
+                putc('['),
+                wasm!("block", wasm!(body)),
+                putc(']'),
+            ];
+        }
+        VariableType::Tuple(types) => {
+            let tuple = || wasm!("local.get", wasm_dollar!(&format!("heap{}", depth)));
+            let mut writes = vec![
+                wasm!("local.set", wasm_dollar!(&format!("heap{}", depth)), value),
+                putc('('),
+            ];
+            for (i, element_type) in types.iter().enumerate() {
+                if i > 0 {
+                    writes.extend(vec![putc(','), putc(' ')]);
+                }
+                writes.extend(write(element_type, field(types, i, tuple()), depth + 1));
+            }
+            writes.push(putc(')'));
+            return writes;
+        }
+    };
+    vec![write]
+}
+
+// A function writing a value of the data type as it would be made, such as `Rect 1.0 2.0`. When
+// `$nested` is set the value is an argument of another constructor, so it is written in
+// parentheses if it has arguments of its own.
+fn write_data(type_name: &str, data_types: &DataTypes) -> Expression {
+    let value = || wasm!("local.get", wasm_dollar!("value"));
+    let constructors = data_types.constructors_of(type_name).unwrap();
+    let depth = constructors
+        .iter()
+        .flat_map(|(_, c)| c.arg_types.iter().map(heap_depth))
+        .max()
+        .unwrap_or(0);
+
+    let mut func = vec![
+        wasm!("func"),
+        wasm_dollar!(&format!("_write_{}", type_name)),
+        wasm!("param", wasm_dollar!("value"), "i32"),
+        wasm!("param", wasm_dollar!("nested"), "i32"),
+    ];
+    for depth in 0..depth {
+        func.push(wasm!(
+            "local",
+            wasm_dollar!(&format!("heap{}", depth)),
+            "i32"
+        ));
+    }
+
+    for (name, constructor) in constructors {
+        let types = constructor.cell_types();
+        let parenthesize = |c: char| {
+            wasm!(
+                "if",
+                wasm!("local.get", wasm_dollar!("nested")),
+                wasm!("then", putc(c))
+            )
+        };
+
+        let mut then = vec![wasm!("then")];
+        if !constructor.arg_types.is_empty() {
+            then.push(parenthesize('('));
+        }
+        then.extend(name.chars().map(putc));
+        for (i, arg_type) in constructor.arg_types.iter().enumerate() {
+            then.push(putc(' '));
+            let arg = field(&types, i + 1, value());
+            match arg_type {
+                VariableType::Data(name) => then.push(wasm!(
+                    "call",
+                    wasm_dollar!(&format!("_write_{}", name)),
+                    arg,
+                    wasm!("i32.const", 1)
+                )),
+                _ => then.extend(write(arg_type, arg, 0)),
+            }
+        }
+        if !constructor.arg_types.is_empty() {
+            then.push(parenthesize(')'));
+        }
+
+        func.push(wasm!(
+            "if",
+            wasm!(
+                "i32.eq",
+                field(&types, 0, value()),
+                wasm!("i32.const", constructor.tag)
+            ),
+            wasm!(then)
+        ));
+    }
+    wasm!(func)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -168,12 +487,13 @@ mod tests {
     use wasmtime::{Engine, Linker, Module, Store};
     use wasmtime_wasi::sync::WasiCtxBuilder;
 
-    #[test]
-    fn test_example() {
-        let source_content = include_str!("../../examples/example.muru");
+    fn run(source_content: &str) -> String {
+        let source_content = parser::layout(source_content).unwrap();
         let mut parse_tree = parser::Parser::parse(parser::Rule::program, &source_content).unwrap();
         let program = Program::from_pest(&mut parse_tree).unwrap();
-        let wasm = program.to_wasm(Lib::new()).unwrap();
+        let wasm = program
+            .to_wasm(Lib::new(), &mut Diagnostics::new("test.muru"))
+            .unwrap();
         let bin = wasm.to_bin().unwrap();
 
         let engine = Engine::default();
@@ -198,7 +518,1040 @@ mod tests {
 
         let mut output = String::new();
         buf.read_to_string(&mut output).unwrap();
+        output
+    }
+
+    // compile source that is expected to fail, returning the first line of each diagnostic
+    fn errors(source_content: &str) -> Vec<String> {
+        let source_content = parser::layout(source_content).unwrap();
+        let mut parse_tree = parser::Parser::parse(parser::Rule::program, &source_content).unwrap();
+        let program = Program::from_pest(&mut parse_tree).unwrap();
+        let mut diagnostics = Diagnostics::new("test.muru");
+        assert!(program.to_wasm(Lib::new(), &mut diagnostics).is_err());
+
+        diagnostics
+            .to_string()
+            .lines()
+            .filter(|l| l.starts_with("test.muru"))
+            .map(|l| l.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_example() {
+        let output = run(include_str!("../../examples/example.muru"));
+        assert_eq!(&output[..], "14\n");
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        assert_eq!(run("main = 1 + 2 * 3 - 8 * 4 + 30\n"), "5\n");
+        assert_eq!(run("main = 10 - 4 - 3\n"), "3\n");
+        assert_eq!(run("main = 2 * (3 + 4)\n"), "14\n");
+        assert_eq!(run("main = (1 + 1 == 2 * 1) ? 7 : 8\n"), "7\n");
+    }
+
+    #[test]
+    fn test_ordering_operators() {
+        let source_content = r#"main = (lt 1 2) + (lte 2 2) + (gt -1 -2) + (gte 1.5 2.5) + (lt 3 2)
+lt :: int -> int -> int
+lt x y = (x < y) ? 1 : 0
+lte :: int -> int -> int
+lte x y = (x <= y) ? 10 : 0
+gt :: int -> int -> int
+gt x y = (x > y) ? 100 : 0
+gte :: float -> float -> int
+gte x y = (x >= y) ? 1000 : 0
+"#;
+        assert_eq!(run(source_content), "111\n");
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        let source_content = r#"main = (check (1 < 2 && 2 < 3)) + (check (1 > 2 || !(2 > 3))) + (check (true && false))
+check :: bool -> int
+check b = b ? 1 : 0
+"#;
+        assert_eq!(run(source_content), "2\n");
+
+        // dividing by zero traps, so these only run if the right hand side is skipped
+        assert_eq!(run("main = (false && 1 / 0 == 1) ? 1 : 2\n"), "2\n");
+        assert_eq!(run("main = (true || 1 / 0 == 1) ? 1 : 2\n"), "1\n");
+    }
+
+    #[test]
+    fn test_logical_operator_arguments() {
+        let source_content = "main = (1 && 2) ? 1 : 2\nother :: bool\nother = !1\n";
+        assert_eq!(
+            errors(source_content),
+            vec![
+                "test.muru:1:9: no implementation of '&&' for int",
+                "test.muru:3:9: no implementation of '!' for int",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_integer_operators() {
+        let source_content = r#"main = (check (7 % 3 == 1)) + (check (-7 % 3 == -1)) + (check (12 & 10 == 8)) + (check (12 | 3 ^ 1 == 14)) + (check (1 << 4 == 16)) + (check (-16 >> 2 == -4)) + (negate 5) + 5
+check :: bool -> int
+check b = b ? 1 : 0
+negate :: int -> int
+negate x = -x
+"#;
+        assert_eq!(run(source_content), "6\n");
+    }
+
+    #[test]
+    fn test_integer_operator_arguments() {
+        let source_content = "main = 1.5 % 2.0\nother :: bool\nother = -true\n";
+        assert_eq!(
+            errors(source_content),
+            vec![
+                "test.muru:1:8: no implementation of '%' for float",
+                "test.muru:3:9: no implementation of '-' for bool",
+            ]
+        );
+
+        // an operand of the wrong type is reported before comparing it with the other one
+        assert_eq!(
+            errors("main = printi 3 + 1\n"),
+            vec!["test.muru:1:8: no implementation of '+' for unit"]
+        );
+    }
+
+    #[test]
+    fn test_operator_types() {
+        let valid = [
+            // int
+            ("7 + 3", "10"),
+            ("7 - 3", "4"),
+            ("7 * 3", "21"),
+            ("-7 / 2", "-3"),
+            ("-7 % 2", "-1"),
+            ("6 & 3", "2"),
+            ("6 | 3", "7"),
+            ("6 ^ 3", "5"),
+            ("7 << 3", "56"),
+            ("-56 >> 3", "-7"),
+            ("-(7)", "-7"),
+            ("7 == 3", "false"),
+            ("7 != 3", "true"),
+            ("-7 < 3", "true"),
+            ("3 <= 3", "true"),
+            ("-7 > 3", "false"),
+            ("3 >= 7", "false"),
+            // int64
+            ("7L + 3L", "10L"),
+            ("7L - 4294967296L", "-4294967289L"),
+            ("4294967296L * 3L", "12884901888L"),
+            ("-7L / 2L", "-3L"),
+            ("-7L % 2L", "-1L"),
+            ("4294967302L & 3L", "2L"),
+            ("4294967302L | 3L", "4294967303L"),
+            ("6L ^ 3L", "5L"),
+            ("1L << 40L", "1099511627776L"),
+            ("-56L >> 3L", "-7L"),
+            ("-(7L)", "-7L"),
+            ("4294967296L == 0L", "false"),
+            ("4294967296L != 0L", "true"),
+            ("-7L < 3L", "true"),
+            ("4294967296L <= 4294967296L", "true"),
+            ("-7L > 3L", "false"),
+            ("3L >= 7L", "false"),
+            // float
+            ("1.5 + 2.25", "3.75"),
+            ("1.5 - 2.25", "-0.75"),
+            ("1.5 * 2.5", "3.75"),
+            ("7.5 / 2.5", "3.0"),
+            ("-(1.5)", "-1.5"),
+            ("1.5 == 1.5", "true"),
+            ("1.5 != 1.5", "false"),
+            ("-1.5 < -2.5", "false"),
+            ("2.5 <= 2.5", "true"),
+            ("-1.5 > -2.5", "true"),
+            ("1.5 >= 2.5", "false"),
+            // double
+            ("1.5d + 2.25d", "3.75d"),
+            ("16777217.0d - 16777216.0d", "1.0d"),
+            ("1.5d * 2.5d", "3.75d"),
+            ("7.5d / 2.5d", "3.0d"),
+            ("-(1.5d)", "-1.5d"),
+            ("16777217.0d == 16777216.0d", "false"),
+            ("16777217.0d != 16777216.0d", "true"),
+            ("-1.5d < -2.5d", "false"),
+            ("2.5d <= 2.5d", "true"),
+            ("-1.5d > -2.5d", "true"),
+            ("1.5d >= 2.5d", "false"),
+            // bool
+            ("true == false", "false"),
+            ("true != false", "true"),
+            ("true && false", "false"),
+            ("true || false", "true"),
+            ("!true", "false"),
+        ];
+        for (expression, expected) in valid.iter() {
+            // the result is compared inside a function returning bool so the operand types differ
+            let source_content = format!(
+                "main = test ? 1 : 0\ntest :: bool\ntest = ({}) == ({})\n",
+                expression, expected
+            );
+            assert_eq!(run(&source_content), "1\n", "{}", expression);
+        }
+
+        let invalid = [
+            ("1 && 2", "&&", "int"),
+            ("1 || 2", "||", "int"),
+            ("!1", "!", "int"),
+            ("1.5 % 2.5", "%", "float"),
+            ("1.5 & 2.5", "&", "float"),
+            ("1.5 | 2.5", "|", "float"),
+            ("1.5 ^ 2.5", "^", "float"),
+            ("1.5 << 2.5", "<<", "float"),
+            ("1.5 >> 2.5", ">>", "float"),
+            ("1.5 && 2.5", "&&", "float"),
+            ("1.5 || 2.5", "||", "float"),
+            ("!1.5", "!", "float"),
+            ("1L && 2L", "&&", "int64"),
+            ("1L || 2L", "||", "int64"),
+            ("!1L", "!", "int64"),
+            ("1.5d % 2.5d", "%", "double"),
+            ("1.5d & 2.5d", "&", "double"),
+            ("1.5d | 2.5d", "|", "double"),
+            ("1.5d ^ 2.5d", "^", "double"),
+            ("1.5d << 2.5d", "<<", "double"),
+            ("1.5d >> 2.5d", ">>", "double"),
+            ("1.5d && 2.5d", "&&", "double"),
+            ("1.5d || 2.5d", "||", "double"),
+            ("!1.5d", "!", "double"),
+            ("true + false", "+", "bool"),
+            ("true - false", "-", "bool"),
+            ("true * false", "*", "bool"),
+            ("true / false", "/", "bool"),
+            ("true % false", "%", "bool"),
+            ("true & false", "&", "bool"),
+            ("true | false", "|", "bool"),
+            ("true ^ false", "^", "bool"),
+            ("true << false", "<<", "bool"),
+            ("true >> false", ">>", "bool"),
+            ("true < false", "<", "bool"),
+            ("true <= false", "<=", "bool"),
+            ("true > false", ">", "bool"),
+            ("true >= false", ">=", "bool"),
+            ("-true", "-", "bool"),
+        ];
+        for (expression, operator, argument_type) in invalid.iter() {
+            assert_eq!(
+                errors(&format!("main = {}\n", expression)),
+                vec![format!(
+                    "test.muru:1:8: no implementation of '{}' for {}",
+                    operator, argument_type
+                )]
+            );
+        }
+    }
+
+    #[test]
+    fn test_mixed_types() {
+        let source_content = r#"main = (less 1.5 2.5) ? (half 3) : 0
+less :: float -> float -> bool
+less x y = x < y
+half :: int -> int
+half n = (n / 2 == 1 && toss 0.5) ? 1 : 2
+toss :: float -> bool
+toss f = ((f > 0.25) ? f : 1.0) < 0.75
+"#;
+        assert_eq!(run(source_content), "1\n");
+    }
+
+    #[test]
+    fn test_wide_types() {
+        let source_content = r#"main = (precise 16777217.0d) + (big 3000000000L ? 10 : 20) + ((fact 20L == 2432902008176640000L) ? 100 : 200)
+big :: int64 -> bool
+big n = n * 2L > 5000000000L
+precise :: double -> int
+precise x = (x - 16777216.0d == 1.0d) ? 1 : 2
+fact :: int64 -> int64
+fact 0L = 1L
+fact n = n * fact (n - 1L)
+"#;
+        assert_eq!(run(source_content), "111\n");
+
+        let source_content = "main = 1 + 1L
+half :: double -> double
+half x = x / 2.0
+";
+        assert_eq!(
+            errors(source_content),
+            vec![
+                "test.muru:1:12: type mismatch error, expected: int, got: int64",
+                "test.muru:3:14: type mismatch error, expected: double, got: float",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conversions() {
+        let source_content = r#"main = (check (toInt 2.75 == 2)) + (check (toInt -2.75 == -2)) + (check (toFloat 3 / 2.0 == 1.5)) + (check (fromBool true + fromBool false == 1)) + (check (toInt 99999999999.0 == 2147483647)) + (check (toInt -99999999999.0 == -2147483648)) + (check (toInt (0.0 / 0.0) == 0)) + (check (fromInt64 4294967297L == 1)) + (check (toInt64 -1 == -1L)) + (check (fromDouble (toDouble 0.1) == 0.1))
+check b = b ? 1 : 0
+"#;
+        assert_eq!(run(source_content), "10\n");
+
+        let source_content = "main = toInt 1.0 2.0\nother :: bool\nother = toFloat 1.5 > 1.0\n";
+        assert_eq!(
+            errors(source_content),
+            vec![
+                "test.muru:1:8: argument error: toInt expected: 1, got: 2",
+                "test.muru:3:17: type mismatch error, expected: int, got: float",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        let source_content = r##"main = let a = prints greeting
+           b = prints "\"#1\" where\tthe \\ isn't\n"
+       in (vowels 'e') + ((fromChar 'A' == 65) ? 100 : 0)
+greeting = "héllo\n"
+vowels c = case c of
+  'a' -> 1000
+  'e' -> 2000
+  _ -> 0
+"##;
+        assert_eq!(
+            run(source_content),
+            "h\u{e9}llo\n\"#1\" where\tthe \\ isn't\n2100\n"
+        );
+
+        let source_content = "main = prints 'a'\nshout :: string -> string\nshout s = s + \"!\"\n";
+        assert_eq!(
+            errors(source_content),
+            vec![
+                "test.muru:1:15: type mismatch error, expected: string, got: char",
+                "test.muru:3:11: no implementation of '+' for string",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_printi() {
+        let cases = [
+            ("0", "0"),
+            ("7", "7"),
+            ("-7", "-7"),
+            ("10", "10"),
+            ("-100", "-100"),
+            ("2147483647", "2147483647"),
+            ("-2147483647 - 1", "-2147483648"),
+        ];
+        for (expression, expected) in cases.iter() {
+            let source_content = format!("main = printi ({})\n", expression);
+            assert_eq!(&run(&source_content), expected, "{}", expression);
+        }
+
+        let source_content = "main = let a = printi 0
+           b = printl (0L - 9223372036854775807L - 1L)
+       in printi (0 - 15)
+";
+        assert_eq!(run(source_content), "0-9223372036854775808-15");
+    }
+
+    #[test]
+    fn test_printf() {
+        let cases = [
+            ("printf 0.0", "0.0"),
+            ("printf (-0.0)", "-0.0"),
+            ("printf (0.0 / 0.0)", "nan"),
+            ("printf (-(0.0 / 0.0))", "nan"),
+            ("printf (1.0 / 0.0)", "inf"),
+            ("printf (-1.0 / 0.0)", "-inf"),
+            ("printf 1.5", "1.5"),
+            ("printf (-12.25)", "-12.25"),
+            ("printf 0.1", "0.1"),
+            ("printf 0.9999999", "1.0"),
+            ("printf 16777216.0", "16777216.0"),
+            ("printd 0.1d", "0.1"),
+            ("printd (-0.0d)", "-0.0"),
+            ("printd 123456789.125d", "123456789.125"),
+            ("printd (1.0d / 0.0d)", "inf"),
+        ];
+        for (expression, expected) in cases.iter() {
+            let source_content = format!("main = {}\n", expression);
+            assert_eq!(&run(&source_content), expected, "{}", expression);
+        }
+    }
+
+    #[test]
+    fn test_scratch_memory() {
+        // printing goes through the scratch area, which leaves the strings after it alone
+        let source_content = r#"main = let a = prints greeting
+           b = printl (0L - 9223372036854775807L - 1L)
+           c = printb false
+           d = printd (0.0d - 1.0d / 3.0d)
+           e = printc (fromChar 'é')
+           f = printc (fromChar '€')
+           g = printc (fromChar '𝄞')
+       in greeting
+greeting = "hello, world"
+"#;
+        assert_eq!(
+            run(source_content),
+            "hello, world-9223372036854775808false-0.333333333333333é€𝄞hello, world\n"
+        );
+    }
+
+    #[test]
+    fn test_main_types() {
+        let cases = [
+            ("main = true", "true\n"),
+            ("main = 1 < 0", "false\n"),
+            ("main = 3.75", "3.75\n"),
+            ("main = 0.1", "0.1\n"),
+            ("main = 0.0d - 2.5d", "-2.5\n"),
+            ("main = 1.0d / 3.0d", "0.333333333333333\n"),
+            ("main = 1.0 / 0.0", "inf\n"),
+            ("main = 0.0 / 0.0", "nan\n"),
+            ("main = 'x'", "x\n"),
+            ("main = \"hi\"", "hi\n"),
+            (
+                "main = -9223372036854775807L - 1L",
+                "-9223372036854775808\n",
+            ),
+            ("main = printi 5", "5"),
+            ("main = ()", ""),
+        ];
+        for (source_content, expected) in cases.iter() {
+            assert_eq!(
+                &run(&format!("{}\n", source_content)),
+                expected,
+                "{}",
+                source_content
+            );
+        }
+    }
+
+    #[test]
+    fn test_local_bindings() {
+        let source_content = r#"main = area 3 + (let x = 2; y = x * 10 in let x = 1.5 in (x > 1.0) ? y : 0) + twice 4
+area :: int -> int
+area r = r * r * pi where pi = 3
+twice 0 = 0
+twice n = d where h = n; d = h + h
+"#;
+        assert_eq!(run(source_content), "55\n");
+
+        assert_eq!(
+            run("main = a\n  where a = b + c\n        b = c * 2\n        c = 3\n"),
+            "9\n"
+        );
+        assert_eq!(run("main = let x = y; y = 4 in x\n"), "4\n");
+        assert_eq!(
+            errors("main = a where a = b; b = a\n"),
+            vec!["test.muru:1:27: function not found error: a"]
+        );
+
+        assert_eq!(
+            errors("main = y where y = 1 + true\n"),
+            vec!["test.muru:1:24: type mismatch error, expected: int, got: bool"]
+        );
+    }
+
+    #[test]
+    fn test_multiline() {
+        let source_content = r#"main = area 3
+    + twice 4 # comments can end a continued line
+
+    + (let a = 1
+           b = 2
+       in a + b)
+area :: int -> int
+area r = r * r * pi
+  where
+    pi = 3
+twice n = d
+  where h = n
+        d = h + h
+"#;
+        assert_eq!(run(source_content), "38\n");
+    }
+
+    #[test]
+    fn test_function_cases() {
+        let source_content = r#"main = (pick 0 5) + (pick 3 5) + (pick 3 0) + (xor true false) + (xor true true)
+pick 0 _ = 1
+pick _ 0 = 10
+pick x y = x * y * 100
+xor true false = 1000
+xor false true = 1000
+xor _ _ = 0
+"#;
+        assert_eq!(run(source_content), "2511\n");
 
-        assert_eq!(&output[..], "1\u{0}4\u{0}\n\u{0}");
+        let source_content = r#"main = (both true true) + (first 1 true)
+both :: bool -> bool -> int
+both true true = 1
+both true false = 2
+both false _ = 3
+first 1 true = 1
+first _ false = 2
+"#;
+        assert_eq!(
+            errors(source_content),
+            vec!["test.muru:6:1: function case missing error: first"]
+        );
+
+        let source_content = r#"main = (both true true) + (other 1)
+both true _ = 1
+both false _ = 2
+both true false = 3
+other x = x
+other 1 = 2
+"#;
+        assert_eq!(
+            errors(source_content),
+            vec![
+                "test.muru:4:1: function case unreachable error: both",
+                "test.muru:6:1: function case unreachable error: other",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_function_guards() {
+        let source_content = r#"main = (classify (0 - 5)) + (classify 0) * 10 + (classify 7) * 100 + (pick 0 true) * 1000
+classify n | n < 0 = 1
+classify n
+  | n > 0 = 2
+classify _ = 3
+pick 0 b | b = 4
+pick _ _ = 5
+"#;
+        assert_eq!(run(source_content), "4231\n");
+
+        let source_content = r#"main = (sign 1) + (half 2)
+sign n | n < 0 = 0
+sign n | n > 0 = 1
+half n | n = n / 2
+half n = n
+half 0 = 0
+"#;
+        assert_eq!(
+            errors(source_content),
+            vec![
+                "test.muru:2:1: function case missing error: sign",
+                "test.muru:4:10: type mismatch error, expected: bool, got: int",
+                "test.muru:6:1: function case unreachable error: half",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_case_expressions() {
+        let source_content = r#"main = (describe 2) + (describe 7) * 10 + (sign (0 - 3)) * 100 + (day 5) * 1000
+describe n = case n % 3 of
+    0 -> 1
+    1 -> 2
+    _ -> 3
+sign n = case n < 0 of true -> 1; false -> 2
+day d = case d of
+  1 -> 1
+  2 -> 2
+  3 -> 3
+  5 -> 5
+  6 -> 6
+  x -> x * 2
+"#;
+        assert_eq!(run(source_content), "5123\n");
+
+        // values in the table, in a gap of it, and either side of it
+        let cases = [
+            ("1", "10"),
+            ("4", "40"),
+            ("3", "99"),
+            ("0", "99"),
+            ("-5", "99"),
+            ("5", "50"),
+            ("6", "99"),
+            ("100", "99"),
+        ];
+        for (value, expected) in cases.iter() {
+            let source_content = format!(
+                "main = f ({})\nf x = case x of 1 -> 10; 2 -> 20; 4 -> 40; 5 -> 50; _ -> 99\n",
+                value
+            );
+            assert_eq!(run(&source_content), format!("{}\n", expected), "{}", value);
+        }
+
+        let source_content = r#"main = (day 4) + (nested 1 2)
+day d = case d of
+  1 -> 1
+  2 -> 2
+  3 -> 3
+  5 -> 5
+  x -> x * 10
+nested a b = case a of
+  1 -> case b of
+    2 -> 3
+    _ -> 4
+  _ -> 5
+"#;
+        assert_eq!(run(source_content), "43\n");
+
+        let source_content = r#"main = (f 1) + (g true) + (h 1)
+f n = case n of 0 -> 1; 1 -> 2
+g b = case b of true -> 1; _ -> 2; false -> 3
+h n = case n of true -> 1; _ -> 2
+"#;
+        assert_eq!(
+            errors(source_content),
+            vec![
+                "test.muru:2:7: case missing error",
+                "test.muru:3:36: case unreachable error",
+                "test.muru:4:17: type mismatch error, expected: int, got: bool",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lists() {
+        let source_content = r#"main = (sum (range 1 10)) + (length ([1, 2, 3] : [[4]])) * 100
+sum :: [int] -> int
+sum [] = 0
+sum (x : xs) = x + sum xs
+range :: int -> int -> [int]
+range a b = (a > b) ? [] : (a : range (a + 1) b)
+length :: [[int]] -> int
+length [] = 0
+length (_ : xs) = 1 + length xs
+"#;
+        assert_eq!(run(source_content), "255\n");
+
+        let source_content = r#"main = map (squares 3)
+squares :: int -> [int]
+squares 0 = []
+squares n = n * n : squares (n - 1)
+map :: [int] -> [bool]
+map [] = []
+map (x : xs) = (x % 2 == 0) : map xs
+"#;
+        assert_eq!(run(source_content), "[false, true, false]\n");
+
+        assert_eq!(
+            run("main = [[1.5, 2.0], [], [-3.0]]\n"),
+            "[[1.5, 2.0], [], [-3.0]]\n"
+        );
+        assert_eq!(run("main = ['a', 'b'] : []\n"), "[[a, b]]\n");
+        assert_eq!(run("main = []\n"), "[]\n");
+    }
+
+    #[test]
+    fn test_list_patterns() {
+        let source_content = r#"main = [pairs [1, 2, 3, 4, 5], second [7], second [7, 8], ones [1, 1, 2], kind [[]]]
+pairs :: [int] -> int
+pairs (x : (y : rest)) = x * y + pairs rest
+pairs _ = 0
+second :: [int] -> int
+second (_ : (y : _)) = y
+second _ = 0
+ones :: [int] -> int
+ones (1 : xs) | (ones xs) > 0 = 1 + ones xs
+ones (1 : _) = 1
+ones _ = 0
+kind :: [[int]] -> int
+kind xs = case xs of
+  [] -> 1
+  ([] : _) -> 2
+  ((_ : _) : _) -> 3
+"#;
+        assert_eq!(run(source_content), "[14, 0, 8, 2, 2]\n");
+
+        let source_content = r#"main = (first [1]) + (rest [[1]]) + (both [] [])
+first :: [int] -> int
+first (x : _) = x
+rest :: [[int]] -> int
+rest [] = 0
+rest ([] : _) = 1
+both :: [int] -> [int] -> int
+both [] _ = 0
+both _ [] = 1
+both (_ : _) (_ : _) = 2
+both [] [] = 3
+"#;
+        assert_eq!(
+            errors(source_content),
+            vec![
+                "test.muru:3:1: function case missing error: first",
+                "test.muru:5:1: function case missing error: rest",
+                "test.muru:11:1: function case unreachable error: both",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_list_types() {
+        assert_eq!(
+            errors("main = [1, 2, true]\n"),
+            vec!["test.muru:1:15: every element of a list must have the same type, expected: int, got: bool"]
+        );
+        assert_eq!(
+            errors("main = 1.5 : [1]\n"),
+            vec!["test.muru:1:8: every element of a list must have the same type, expected: int, got: float"]
+        );
+        assert_eq!(
+            errors("main = [[1], [true]]\n"),
+            vec!["test.muru:1:14: every element of a list must have the same type, expected: [int], got: [bool]"]
+        );
+        assert_eq!(
+            errors("main = 1 : 2\n"),
+            vec!["test.muru:1:12: no implementation of ':' for int"]
+        );
+        assert_eq!(
+            errors("main = [1] == [1]\n"),
+            vec!["test.muru:1:8: no implementation of '==' for [int]"]
+        );
+        assert_eq!(
+            errors("main = f [true]\nf :: [int] -> int\nf _ = 1\n"),
+            vec!["test.muru:1:8: type mismatch error, expected: [int], got: [bool]"]
+        );
+        assert_eq!(
+            errors("main = f 1\nf :: int -> int\nf [] = 1\nf _ = 2\n"),
+            vec!["test.muru:3:3: type mismatch error, expected: int, got: [_]"]
+        );
+        assert_eq!(
+            errors("main = first []\nfirst (x : _) = x\nfirst [] = 0\n"),
+            vec!["test.muru:3:12: untyped function error: could not determine type for function first"]
+        );
+    }
+
+    #[test]
+    fn test_list_garbage() {
+        // builds and drops lists taking up far more memory than there is, keeping the ones the
+        // functions still refer to
+        let source_content = r#"main = churn 2000 0
+churn :: int -> int -> int
+churn 0 total = total
+churn n total = let xs = range 1 100
+                    ys = range 1 n
+                in churn (n - 1) (total + (sum xs) - (sum ys) + n * (n + 1) / 2)
+sum :: [int] -> int
+sum [] = 0
+sum (x : xs) = x + sum xs
+range :: int -> int -> [int]
+range a b = (a > b) ? [] : (a : range (a + 1) b)
+"#;
+        assert_eq!(run(source_content), "10100000\n");
+    }
+
+    #[test]
+    fn test_tuples() {
+        let source_content = r#"main = (divmod 17 5, swap (3, true), first (1.5, 'c'), [(1, [2]), (3, [])])
+divmod :: int -> int -> (int, int)
+divmod a b = (a / b, a % b)
+swap :: (int, bool) -> (bool, int)
+swap (x, y) = (y, x)
+first (x, _) = x
+"#;
+        assert_eq!(
+            run(source_content),
+            "((3, 2), (true, 3), 1.5, [(1, [2]), (3, [])])\n"
+        );
+    }
+
+    #[test]
+    fn test_tuple_patterns() {
+        let source_content = r#"main = [sum [(1, 2), (3, 4)], classify (0, true), classify (1, true), classify (1, false)]
+sum :: [(int, int)] -> int
+sum [] = 0
+sum ((a, b) : rest) = a + b + sum rest
+classify :: (int, bool) -> int
+classify (0, _) = 1
+classify (_, true) = 2
+classify (_, false) = 3
+"#;
+        assert_eq!(run(source_content), "[10, 1, 2, 3]\n");
+
+        let source_content = r#"main = f (1, true) + g (1, [])
+f :: (int, bool) -> int
+f (1, true) = 1
+f (_, false) = 2
+g :: (int, [bool]) -> int
+g (a, []) = a
+g (a, (x : _)) = a
+g (1, _) = 0
+"#;
+        assert_eq!(
+            errors(source_content),
+            vec![
+                "test.muru:3:1: function case missing error: f",
+                "test.muru:8:1: function case unreachable error: g",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tuple_types() {
+        assert_eq!(
+            errors("main = f (1, 2, 3)\nf :: (int, int) -> int\nf (a, b) = a\n"),
+            vec!["test.muru:1:8: tuple arity mismatch error, expected 2 elements: (int, int), got 3: (int, int, int)"]
+        );
+        assert_eq!(
+            errors("main = f (1, 2)\nf :: (int, int) -> int\nf (a, b, c) = a\n"),
+            vec!["test.muru:3:3: tuple arity mismatch error, expected 2 elements: (int, int), got 3: (_, _, _)"]
+        );
+        assert_eq!(
+            errors("main = f 1\nf :: int -> (int, int)\nf x = (x, x, x)\n"),
+            vec!["test.muru:3:7: tuple arity mismatch error, expected 2 elements: (int, int), got 3: (int, int, int)"]
+        );
+        assert_eq!(
+            errors("main = f (1, true)\nf :: (int, int) -> int\nf (a, b) = a\n"),
+            vec!["test.muru:1:8: type mismatch error, expected: (int, int), got: (int, bool)"]
+        );
+        assert_eq!(
+            errors("main = f 1\nf (a, b) = a\n"),
+            vec!["test.muru:2:3: type mismatch error, expected: int, got: (_, _)"]
+        );
+        assert_eq!(
+            errors("main = (1, 2) == (1, 2)\n"),
+            vec!["test.muru:1:8: no implementation of '==' for (int, int)"]
+        );
+        assert_eq!(
+            errors(&format!(
+                "main = f 1\nf x = ({})\n",
+                vec!["x"; 33].join(", ")
+            )),
+            vec!["test.muru:2:7: not implemented error: tuples of more than 32 elements"]
+        );
+    }
+
+    #[test]
+    fn test_tuple_garbage() {
+        // tuples hold on to the lists in them while more are allocated
+        let source_content = r#"main = churn 2000 0
+churn :: int -> int -> int
+churn 0 total = total
+churn n total = let p = split (range 1 100) ([], [])
+                in churn (n - 1) (total + check p)
+check :: ([int], [int]) -> int
+check (evens, odds) = sum evens - sum odds
+split :: [int] -> ([int], [int]) -> ([int], [int])
+split [] acc = acc
+split (x : xs) (evens, odds) = split xs ((x % 2 == 0) ? (x : evens) : evens, (x % 2 == 0) ? odds : (x : odds))
+sum :: [int] -> int
+sum [] = 0
+sum (x : xs) = x + sum xs
+range :: int -> int -> [int]
+range a b = (a > b) ? [] : (a : range (a + 1) b)
+"#;
+        assert_eq!(run(source_content), "100000\n");
+    }
+
+    #[test]
+    fn test_data_types() {
+        let source_content = r#"data Shape = Circle float | Rect float float
+data IntList = Nil | Cons int IntList
+data Tree = Leaf | Node Tree int Tree
+main = (area (Circle 2.0), area (Rect 1.5 2.0), build 3, [Circle 1.0], insert 2 (insert 1 (insert 3 Leaf)))
+area :: Shape -> float
+area (Circle r) = 3.0 * r * r
+area (Rect w h) = w * h
+build :: int -> IntList
+build 0 = Nil
+build n = Cons n (build (n - 1))
+insert :: int -> Tree -> Tree
+insert x Leaf = Node Leaf x Leaf
+insert x (Node l v r) = (x < v) ? (Node (insert x l) v r) : (Node l v (insert x r))
+"#;
+        assert_eq!(
+            run(source_content),
+            "(12.0, 3.0, Cons 3 (Cons 2 (Cons 1 Nil)), [Circle 1.0], Node (Node Leaf 1 (Node Leaf 2 Leaf)) 3 Leaf)\n"
+        );
+
+        let source_content = r#"data Shape = Circle float | Rect float float
+main = case Rect 1.0 2.0 of
+    (Circle r) -> r
+    (Rect w h) -> w * h
+"#;
+        assert_eq!(run(source_content), "2.0\n");
+    }
+
+    #[test]
+    fn test_data_patterns() {
+        let source_content = r#"data Shape = Circle float | Rect float float
+main = area (Circle 1.0) + perimeter (Rect 1.0 2.0)
+area :: Shape -> float
+area (Circle r) = r
+perimeter :: Shape -> float
+perimeter (Circle r) = r
+perimeter _ = 1.0
+perimeter (Rect w h) = w
+"#;
+        assert_eq!(
+            errors(source_content),
+            vec![
+                "test.muru:4:1: function case missing error: area",
+                "test.muru:8:1: function case unreachable error: perimeter",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_data_type_errors() {
+        assert_eq!(
+            errors("main = 1\nf :: Shape -> int\nf _ = 1\n"),
+            vec!["test.muru:2:6: type not found error: Shape"]
+        );
+        assert_eq!(
+            errors("data Shape = Circle float | Circle int\ndata Shape = Square\nmain = 1\n"),
+            vec![
+                "test.muru:1:29: function already defined error: Circle",
+                "test.muru:2:6: type already defined error: Shape",
+            ]
+        );
+        assert_eq!(
+            errors("data Shape = Circle float\nmain = Circle 1.0\nCircle x = 1\n"),
+            vec![
+                "test.muru:1:14: function already defined error: Circle",
+                "test.muru:3:1: capital name error: Circle, only types and constructors start with a capital letter",
+            ]
+        );
+        // a capital letter makes a name a constructor, so it can't be used for anything else
+        assert_eq!(
+            errors("main = X + f 1\nX = 1\nf X = let Y = 2 in Y\n"),
+            vec![
+                "test.muru:2:1: capital name error: X, only types and constructors start with a capital letter",
+                "test.muru:3:3: constructor not found error: X",
+            ]
+        );
+        assert_eq!(
+            errors("main = y where Y = 1; y = 2\n"),
+            vec!["test.muru:1:16: capital name error: Y, only types and constructors start with a capital letter"]
+        );
+        assert_eq!(
+            errors("data Shape = Circle float\nmain = f (Circle 1.0)\nf :: Shape -> int\nf (Square x) = 1\n"),
+            vec!["test.muru:4:4: constructor not found error: Square"]
+        );
+        assert_eq!(
+            errors("data Shape = Circle float\ndata Colour = Red\nmain = f (Circle 1.0)\nf :: Shape -> int\nf Red = 1\n"),
+            vec!["test.muru:5:3: type mismatch error, expected: Shape, got: Colour"]
+        );
+        assert_eq!(
+            errors("data Shape = Circle float\nmain = f (Circle 1.0)\nf :: Shape -> float\nf (Circle x y) = x\n"),
+            vec!["test.muru:4:3: argument error: Circle expected: 1, got: 2"]
+        );
+        assert_eq!(
+            errors("data Shape = Circle float\nmain = Circle 1\n"),
+            vec!["test.muru:2:15: type mismatch error, expected: float, got: int"]
+        );
+        assert_eq!(
+            errors("data Shape = Circle float\nmain = Circle 1.0 == Circle 1.0\n"),
+            vec!["test.muru:2:8: no implementation of '==' for Shape"]
+        );
+    }
+
+    #[test]
+    fn test_data_garbage() {
+        // trees are built and dropped while the lists they are built from are still in use
+        let source_content = r#"data IntList = Nil | Cons int IntList
+data Tree = Leaf | Node Tree int Tree
+main = churn 1000 0
+churn :: int -> int -> int
+churn 0 total = total
+churn n total = let t = fromList (build 60) Leaf
+                    xs = build n
+                in churn (n - 1) (total + sumTree t + size xs - n)
+build :: int -> IntList
+build 0 = Nil
+build n = Cons ((n * 37) % 101) (build (n - 1))
+size :: IntList -> int
+size Nil = 0
+size (Cons _ rest) = 1 + size rest
+fromList :: IntList -> Tree -> Tree
+fromList Nil t = t
+fromList (Cons x rest) t = fromList rest (insert x t)
+insert :: int -> Tree -> Tree
+insert x Leaf = Node Leaf x Leaf
+insert x (Node l v r) = (x < v) ? (Node (insert x l) v r) : (Node l v (insert x r))
+sumTree :: Tree -> int
+sumTree Leaf = 0
+sumTree (Node l v r) = sumTree l + v + sumTree r
+"#;
+        assert_eq!(run(source_content), "3070000\n");
+    }
+
+    #[test]
+    fn test_error_location() {
+        let source_content = "five = 5\nmain = five + true\n";
+        let mut parse_tree = parser::Parser::parse(parser::Rule::program, source_content).unwrap();
+        let program = Program::from_pest(&mut parse_tree).unwrap();
+        let mut diagnostics = Diagnostics::new("test.muru");
+        assert!(program.to_wasm(Lib::new(), &mut diagnostics).is_err());
+
+        assert_eq!(
+            diagnostics.to_string(),
+            "test.muru:2:15: type mismatch error, expected: int, got: bool\n  |\n2 | main = five + true\n  |               ^^^^\n"
+        );
+    }
+
+    #[test]
+    fn test_multiple_errors() {
+        let source_content = r#"main = first + second
+first = 1 + true
+second = false ? 1 : 2
+third :: int -> int
+third x = x == 1
+unused = 4
+"#;
+        let mut parse_tree = parser::Parser::parse(parser::Rule::program, source_content).unwrap();
+        let program = Program::from_pest(&mut parse_tree).unwrap();
+        let mut diagnostics = Diagnostics::new("test.muru");
+        assert!(program.to_wasm(Lib::new(), &mut diagnostics).is_err());
+
+        let lines = diagnostics
+            .to_string()
+            .lines()
+            .filter(|l| l.starts_with("test.muru"))
+            .map(|l| l.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            lines,
+            vec![
+                "test.muru:2:13: type mismatch error, expected: int, got: bool",
+                "test.muru:5:11: type mismatch error, expected: int, got: bool",
+                "test.muru:6:1: warning: unused function unused",
+            ]
+        );
+        assert_eq!(
+            diagnostics.summary(),
+            "could not compile test.muru due to 2 errors; 1 warning emitted"
+        );
+
+        // every argument, element and branch is checked, even once one of them has failed
+        assert_eq!(
+            errors("main = f (1 + true) [2 + true, 3 + true] (4 + true, 5)\nf x y z = 1\n"),
+            vec![
+                "test.muru:1:15: type mismatch error, expected: int, got: bool",
+                "test.muru:1:26: type mismatch error, expected: int, got: bool",
+                "test.muru:1:36: type mismatch error, expected: int, got: bool",
+                "test.muru:1:47: type mismatch error, expected: int, got: bool",
+            ]
+        );
+        assert_eq!(
+            errors("main = (1 + true) ? (2 + true) : (3 + true)\n"),
+            vec![
+                "test.muru:1:13: type mismatch error, expected: int, got: bool",
+                "test.muru:1:26: type mismatch error, expected: int, got: bool",
+                "test.muru:1:39: type mismatch error, expected: int, got: bool",
+            ]
+        );
+
+        // a function whose call was rejected is still used
+        assert_eq!(
+            errors("main = f 1 2\nf x = x\n"),
+            vec!["test.muru:1:8: argument error: f expected: 1, got: 2"]
+        );
+        assert_eq!(
+            errors("main = f (D 1)\nf x = 1\n"),
+            vec!["test.muru:1:11: function not found error: D"]
+        );
     }
 }
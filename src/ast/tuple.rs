@@ -0,0 +1,73 @@
+use crate::ast::context::Context;
+use crate::ast::expression::Expression;
+use crate::ast::locals::Locals;
+use crate::ast::util::{checked_type, span_into_default, validate_all};
+use crate::ast::variable::VariableType;
+use crate::err::{CompileError, Location, NotImplementedError};
+use crate::parser::Rule;
+use crate::stdlib::{set_field, tuple, MAX_TUPLE_ELEMENTS};
+use crate::wasm;
+use pest::Span;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+// `(1, true)`
+#[derive(Debug, FromPest)]
+#[pest_ast(rule(Rule::tuple))]
+pub struct Tuple<'a> {
+    pub elements: Vec<Expression<'a>>,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+    // the type of the tuple, known once it has been validated
+    #[pest_ast(outer(with(span_into_default)))]
+    pub tuple_type: RefCell<Option<VariableType>>,
+}
+
+impl<'a> Tuple<'a> {
+    pub fn validate(
+        &self,
+        ctx: &mut Context<'a, '_>,
+        local_types: &HashMap<&'a str, VariableType>,
+    ) -> Result<VariableType, Box<dyn CompileError>> {
+        if self.elements.len() > MAX_TUPLE_ELEMENTS {
+            return Err(Box::new(NotImplementedError {
+                sub: format!("tuples of more than {} elements", MAX_TUPLE_ELEMENTS),
+                location: Location::new(&self.span),
+            }));
+        }
+
+        let results = self
+            .elements
+            .iter()
+            .map(|e| e.validate(ctx, local_types))
+            .collect();
+        let types = validate_all(results, ctx.diagnostics)?;
+
+        let tuple_type = VariableType::Tuple(types);
+        *self.tuple_type.borrow_mut() = Some(tuple_type.clone());
+        Ok(tuple_type)
+    }
+
+    // The cell is allocated before the elements are evaluated and kept on the shadow stack, so
+    // each element is safe on the heap as soon as it is stored.
+    pub fn to_wasm(&self, locals: &mut Locals<'a>) -> wasm::Expression {
+        let tuple_type = checked_type(&self.tuple_type);
+        let types = match &tuple_type {
+            VariableType::Tuple(types) => types.clone(),
+            _ => unreachable!(),
+        };
+        let cell = locals.temporary(tuple_type.clone());
+
+        let mut block = vec![
+            wasm!("block"),
+            wasm!("result", "i32"),
+            wasm!("local.set", cell, locals.root(&tuple_type, tuple(&types))),
+        ];
+        for (i, element) in self.elements.iter().enumerate() {
+            let value = element.to_wasm(locals);
+            block.push(set_field(&types, i, wasm!("local.get", cell), value));
+        }
+        block.push(wasm!("local.get", cell));
+        wasm!(block)
+    }
+}
@@ -1,10 +1,99 @@
+use crate::ast::variable::VariableType;
+use crate::err::{CompileError, Diagnostics, Location, TupleArityError, TypeMismatchError};
 use pest::Span;
+use std::cell::RefCell;
 use std::marker::PhantomData;
 
 pub fn span_into_str(span: Span) -> &str {
     span.as_str()
 }
 
+// literals with a suffix for their type, such as `1L`
+pub fn str_without_suffix(s: &str) -> &str {
+    &s[..s.len() - 1]
+}
+
+// the text of a quoted char or string literal, with its escapes replaced
+pub fn span_into_unescaped(span: Span) -> String {
+    let quoted = span.as_str();
+    let mut chars = quoted[1..quoted.len() - 1].chars();
+    let mut unescaped = String::new();
+    while let Some(c) = chars.next() {
+        unescaped.push(match c {
+            '\\' => match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some('0') => '\0',
+                // the grammar only allows escaping quotes and backslashes otherwise
+                Some(c) => c,
+                None => unreachable!(),
+            },
+            c => c,
+        });
+    }
+    unescaped
+}
+
+pub fn string_into_char(s: String) -> char {
+    s.chars().next().unwrap()
+}
+
 pub fn span_into_phantomdata(_: Span) -> PhantomData<()> {
     PhantomData
 }
+
+pub fn span_into_default<T: Default>(_: Span) -> T {
+    T::default()
+}
+
+// types are recorded on the ast by validate, which always runs before lowering
+pub fn checked_type(t: &RefCell<Option<VariableType>>) -> VariableType {
+    t.borrow()
+        .clone()
+        .expect("expression was lowered before it was type checked")
+}
+
+// the error for a value of type `got` where `expected` is needed, which for tuples with a
+// different number of elements says so
+pub fn type_mismatch(
+    expected: VariableType,
+    got: VariableType,
+    location: Location,
+) -> Box<dyn CompileError> {
+    match (expected.arity(), got.arity()) {
+        (Some(e), Some(g)) if e != g => Box::new(TupleArityError {
+            expected,
+            got,
+            location,
+        }),
+        _ => Box::new(TypeMismatchError {
+            expected,
+            got,
+            location,
+        }),
+    }
+}
+
+// Takes the results of validating several parts of an expression, all of which have already been
+// checked, so an error in one doesn't hide the errors in the others. Returns their types if every
+// part is valid. Otherwise returns the first error and adds the rest to the diagnostics, so each
+// is reported once.
+pub fn validate_all(
+    results: Vec<Result<VariableType, Box<dyn CompileError>>>,
+    diagnostics: &mut Diagnostics,
+) -> Result<Vec<VariableType>, Box<dyn CompileError>> {
+    let mut types = vec![];
+    let mut first_error = None;
+    for result in results {
+        match result {
+            Ok(t) => types.push(t),
+            Err(e) if first_error.is_none() => first_error = Some(e),
+            Err(e) => diagnostics.error(e),
+        }
+    }
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(types),
+    }
+}
@@ -1,21 +1,50 @@
-use crate::ast::util::span_into_str;
+use crate::ast::data::Data;
+use crate::ast::util::{
+    span_into_default, span_into_str, span_into_unescaped, str_without_suffix, string_into_char,
+};
 use crate::parser::Rule;
 use crate::{wasm, wasm::Expression};
+use pest::Span;
+use std::cell::Cell;
 use std::fmt;
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum VariableType {
     Bool,
+    Char,
+    // a type declared by the program with `data`, by name
+    Data(String),
+    Double,
     Float,
     Int,
+    Int64,
+    List(Box<VariableType>),
+    String,
+    Tuple(Vec<VariableType>),
+    // the result of functions such as printi that are only called for what they do
+    Unit,
+    // the elements of `[]`, which can be anything until it meets a list that says otherwise
+    Unknown,
 }
 
 impl fmt::Display for VariableType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             VariableType::Int => write!(f, "int"),
+            VariableType::Int64 => write!(f, "int64"),
             VariableType::Float => write!(f, "float"),
+            VariableType::Double => write!(f, "double"),
             VariableType::Bool => write!(f, "bool"),
+            VariableType::Char => write!(f, "char"),
+            VariableType::Data(name) => write!(f, "{}", name),
+            VariableType::String => write!(f, "string"),
+            VariableType::Unit => write!(f, "unit"),
+            VariableType::List(t) => write!(f, "[{}]", t),
+            VariableType::Tuple(ts) => {
+                let ts: Vec<String> = ts.iter().map(|t| t.to_string()).collect();
+                write!(f, "({})", ts.join(", "))
+            }
+            VariableType::Unknown => write!(f, "_"),
         }
     }
 }
@@ -24,65 +53,238 @@ impl VariableType {
     pub fn to_wasm(&self) -> Expression {
         match self {
             VariableType::Bool => wasm!("i32"),
+            VariableType::Char => wasm!("i32"),
+            // the address of a cell on the heap with the tag of its constructor followed by its
+            // arguments
+            VariableType::Data(_) => wasm!("i32"),
+            VariableType::Double => wasm!("f64"),
             VariableType::Float => wasm!("f32"),
             VariableType::Int => wasm!("i32"),
+            VariableType::Int64 => wasm!("i64"),
+            // the address of the string in the low 32 bits and its length in bytes in the high 32
+            VariableType::String => wasm!("i64"),
+            // there is only one unit, so its value is always 0
+            VariableType::Unit => wasm!("i32"),
+            // the address of the first cell on the heap, or 0 for the empty list
+            VariableType::List(_) => wasm!("i32"),
+            // The address of a cell on the heap with a field for each element. Returning them as
+            // multiple values would need the multi-value proposal, which wabt doesn't support.
+            VariableType::Tuple(_) => wasm!("i32"),
+            // nothing of this type is ever made, so it doesn't matter
+            VariableType::Unknown => wasm!("i32"),
         }
     }
+
+    // types that numeric operators such as `+` and `<` work on
+    pub fn is_number(&self) -> bool {
+        self.is_integer() || self.is_float()
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(self, VariableType::Int | VariableType::Int64)
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, VariableType::Float | VariableType::Double)
+    }
+
+    // values that are addresses on the heap, which the collector needs to be able to find
+    pub fn is_heap(&self) -> bool {
+        matches!(
+            self,
+            VariableType::Data(_) | VariableType::List(_) | VariableType::Tuple(_)
+        )
+    }
+
+    // The type two values must share, such as the branches of a ternary, or None if they can't.
+    // The elements of `[]` take on the type of the elements of the other list.
+    pub fn unify(&self, other: &VariableType) -> Option<VariableType> {
+        match (self, other) {
+            (VariableType::Unknown, t) | (t, VariableType::Unknown) => Some(t.clone()),
+            (VariableType::List(a), VariableType::List(b)) => {
+                Some(VariableType::List(Box::new(a.unify(b)?)))
+            }
+            (VariableType::Tuple(a), VariableType::Tuple(b)) if a.len() == b.len() => {
+                let ts = a.iter().zip(b).map(|(a, b)| a.unify(b));
+                Some(VariableType::Tuple(ts.collect::<Option<_>>()?))
+            }
+            (a, b) if a == b => Some(a.clone()),
+            _ => None,
+        }
+    }
+
+    // the number of elements of a tuple type
+    pub fn arity(&self) -> Option<usize> {
+        match self {
+            VariableType::Tuple(ts) => Some(ts.len()),
+            _ => None,
+        }
+    }
+
+    // whether a value of type `got` can be used where this type is expected
+    pub fn accepts(&self, got: &VariableType) -> bool {
+        self.unify(got).as_ref() == Some(self)
+    }
 }
 
-#[derive(Debug, FromPest, Copy, Clone)]
+#[derive(Debug, FromPest, Clone)]
 #[pest_ast(rule(Rule::bool))]
-pub struct Bool {
+pub struct Bool<'a> {
     #[pest_ast(outer(with(span_into_str), with(str::parse), with(Result::unwrap)))]
     pub val: bool,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
 }
 
-#[derive(Debug, FromPest, Copy, Clone)]
+#[derive(Debug, FromPest, Clone)]
+#[pest_ast(rule(Rule::char))]
+pub struct Char<'a> {
+    #[pest_ast(outer(with(span_into_unescaped), with(string_into_char)))]
+    pub val: char,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+}
+
+#[derive(Debug, FromPest, Clone)]
+#[pest_ast(rule(Rule::double))]
+pub struct Double<'a> {
+    #[pest_ast(outer(
+        with(span_into_str),
+        with(str_without_suffix),
+        with(str::parse),
+        with(Result::unwrap)
+    ))]
+    pub val: f64,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+}
+
+#[derive(Debug, FromPest, Clone)]
 #[pest_ast(rule(Rule::float))]
-pub struct Float {
+pub struct Float<'a> {
     #[pest_ast(outer(with(span_into_str), with(str::parse), with(Result::unwrap)))]
-    pub val: f64,
+    pub val: f32,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
 }
 
-#[derive(Debug, FromPest, Copy, Clone)]
+#[derive(Debug, FromPest, Clone)]
 #[pest_ast(rule(Rule::int))]
-pub struct Int {
+pub struct Int<'a> {
     #[pest_ast(outer(with(span_into_str), with(str::parse), with(Result::unwrap)))]
+    pub val: i32,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+}
+
+#[derive(Debug, FromPest, Clone)]
+#[pest_ast(rule(Rule::int64))]
+pub struct Int64<'a> {
+    #[pest_ast(outer(
+        with(span_into_str),
+        with(str_without_suffix),
+        with(str::parse),
+        with(Result::unwrap)
+    ))]
     pub val: i64,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
 }
 
-#[derive(Debug, FromPest, Copy, Clone)]
+#[derive(Debug, FromPest, Clone)]
+#[pest_ast(rule(Rule::unit))]
+pub struct Unit<'a> {
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+}
+
+#[derive(Debug, FromPest, Clone)]
 #[pest_ast(rule(Rule::literal))]
-pub enum Variable {
-    Bool(Bool),
-    Float(Float),
-    Int(Int),
+pub enum Variable<'a> {
+    Bool(Bool<'a>),
+    Char(Char<'a>),
+    Double(Double<'a>),
+    Float(Float<'a>),
+    Int(Int<'a>),
+    Int64(Int64<'a>),
+    Unit(Unit<'a>),
 }
 
-impl Variable {
+impl<'a> Variable<'a> {
+    pub fn span(&self) -> &Span<'a> {
+        match self {
+            Variable::Bool(l) => &l.span,
+            Variable::Char(l) => &l.span,
+            Variable::Double(l) => &l.span,
+            Variable::Float(l) => &l.span,
+            Variable::Int(l) => &l.span,
+            Variable::Int64(l) => &l.span,
+            Variable::Unit(l) => &l.span,
+        }
+    }
+
     pub fn get_type(&self) -> VariableType {
         match self {
             Variable::Bool(_) => VariableType::Bool,
+            Variable::Char(_) => VariableType::Char,
+            Variable::Double(_) => VariableType::Double,
             Variable::Float(_) => VariableType::Float,
             Variable::Int(_) => VariableType::Int,
+            Variable::Int64(_) => VariableType::Int64,
+            Variable::Unit(_) => VariableType::Unit,
         }
     }
 
     pub fn to_wasm(&self) -> Expression {
         match self {
             Variable::Int(l) => wasm!("i32.const", l.val),
+            Variable::Int64(l) => wasm!("i64.const", l.val),
             Variable::Float(l) => wasm!("f32.const", l.val),
+            Variable::Double(l) => wasm!("f64.const", l.val),
+            Variable::Unit(_) => wasm!("i32.const", 0),
             Variable::Bool(l) => wasm!("i32.const", l.val as i32),
+            Variable::Char(l) => wasm!("i32.const", l.val as i32),
         }
     }
 }
 
-impl fmt::Display for Variable {
+impl fmt::Display for Variable<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Variable::Int(v) => write!(f, "{}", v.val),
+            Variable::Int64(v) => write!(f, "{}L", v.val),
             Variable::Float(v) => write!(f, "{}", v.val),
+            Variable::Double(v) => write!(f, "{}d", v.val),
+            Variable::Unit(_) => write!(f, "()"),
             Variable::Bool(v) => write!(f, "{}", v.val),
+            Variable::Char(v) => write!(f, "{:?}", v.val),
         }
     }
 }
+
+#[derive(Debug, FromPest)]
+#[pest_ast(rule(Rule::string))]
+pub struct StringLiteral<'a> {
+    #[pest_ast(outer(with(span_into_unescaped)))]
+    pub val: String,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+    // where the string is stored, known once the literal has been validated
+    #[pest_ast(outer(with(span_into_default)))]
+    pub address: Cell<Option<usize>>,
+}
+
+impl<'a> StringLiteral<'a> {
+    pub fn validate(&self, data: &mut Data) -> VariableType {
+        self.address.set(Some(data.string(&self.val)));
+        VariableType::String
+    }
+
+    pub fn to_wasm(&self) -> Expression {
+        let address = self
+            .address
+            .get()
+            .expect("string was lowered before it was type checked");
+        wasm!("i64.const", (self.val.len() as i64) << 32 | address as i64)
+    }
+}
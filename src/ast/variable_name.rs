@@ -1,11 +1,29 @@
 use crate::ast::util::span_into_str;
+use crate::err::{CapitalNameError, CompileError, Location};
 use crate::parser::Rule;
+use pest::Span;
 
-#[derive(Debug, FromPest, Copy, Clone)]
+#[derive(Debug, FromPest, Clone)]
 #[pest_ast(rule(Rule::variable))]
 pub struct VariableName<'a> {
     #[pest_ast(outer(with(span_into_str)))]
     pub name: &'a str,
+    #[pest_ast(outer())]
+    pub span: Span<'a>,
+}
+
+impl<'a> VariableName<'a> {
+    // Names starting with a capital letter are left for types and constructors, which is what
+    // they are taken to be in patterns, so functions and bindings can't be named that way.
+    pub fn check_lowercase(&self) -> Result<(), Box<dyn CompileError>> {
+        if self.name.starts_with(|c: char| c.is_ascii_uppercase()) {
+            return Err(Box::new(CapitalNameError {
+                name: self.name.to_string(),
+                location: Location::new(&self.span),
+            }));
+        }
+        Ok(())
+    }
 }
 
 impl std::fmt::Display for VariableName<'_> {
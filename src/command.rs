@@ -3,9 +3,17 @@ mod run;
 
 use build::Build;
 use clap::Clap;
+use from_pest::FromPest;
+use log::LevelFilter;
 use run::Run;
 use std::error;
 
+use crate::ast::Program;
+use crate::err;
+use crate::parser;
+use crate::stdlib;
+use crate::wasm;
+
 #[derive(Clap, Debug)]
 pub enum SubCommand {
     Build(Build),
@@ -20,3 +28,37 @@ impl SubCommand {
         }
     }
 }
+
+// Compile muru source to a wasm module, adding any errors and warnings to the diagnostics
+fn compile(
+    source_content: &str,
+    diagnostics: &mut err::Diagnostics,
+    level_filter: LevelFilter,
+) -> Result<wasm::Expression, Box<dyn error::Error>> {
+    let source_content =
+        parser::layout(source_content).map_err(|e| diagnostics.abort(Box::new(e)))?;
+    let mut parse_tree =
+        parser::parse(&source_content).map_err(|e| diagnostics.abort(Box::new(e)))?;
+
+    if log::Level::Debug <= level_filter {
+        println!("parse tree:\n{:#?}", parse_tree);
+    }
+
+    let program = Program::from_pest(&mut parse_tree).map_err(|e| {
+        diagnostics.abort(Box::new(err::StandardError {
+            s: format!("unable to build the syntax tree: {:?}", e),
+        }))
+    })?;
+
+    if log::Level::Debug <= level_filter {
+        println!("ast:\n{:#?}", program);
+    }
+
+    let wasm = program.to_wasm(stdlib::Lib::new(), diagnostics)?;
+
+    if log::Level::Debug <= level_filter {
+        println!("wast:\n{}", wasm.to_pretty(4));
+    }
+
+    Ok(wasm)
+}
@@ -1,15 +1,10 @@
-use ast::Program;
+use super::compile;
 use clap::Clap;
-use from_pest::FromPest;
 use log::LevelFilter;
-use pest::Parser;
 use std::error;
 use std::io::Write;
 
-use crate::ast;
 use crate::err;
-use crate::parser;
-use crate::stdlib;
 
 // Build a muru program
 #[derive(Clap, Debug)]
@@ -65,23 +60,10 @@ impl Build {
             println!("source:\n{}", source_content);
         }
 
-        let mut parse_tree = parser::Parser::parse(parser::Rule::program, &source_content)?;
-
-        if log::Level::Debug <= level_filter {
-            println!("parse tree:\n{:#?}", parse_tree);
-        }
-
-        let program = Program::from_pest(&mut parse_tree).unwrap();
-
-        if log::Level::Debug <= level_filter {
-            println!("ast:\n{:#?}", program);
-        }
-
-        let wasm = program.to_wasm(stdlib::Lib::new())?;
-
-        if log::Level::Debug <= level_filter {
-            println!("wast:\n{}", wasm.to_pretty(4));
-        }
+        let mut diagnostics = err::Diagnostics::new(source);
+        let wasm = compile(&source_content, &mut diagnostics, level_filter);
+        eprint!("{}", diagnostics);
+        let wasm = wasm?;
 
         let pretty = wasm.to_pretty(4);
         let mut file = std::fs::File::create(std::path::Path::new(&wast))?;
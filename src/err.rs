@@ -1,23 +1,51 @@
 mod argument_error;
+mod capital_name_error;
+mod case_missing_error;
+mod case_unreachable_error;
+mod compile_error;
+mod constructor_not_found_error;
+mod diagnostics;
 mod function_already_defined_error;
 mod function_case_missing_error;
 mod function_case_unreachable_error;
 mod function_not_found_error;
+mod list_element_error;
+mod location;
 mod no_function_matches_error;
 mod not_implemented_error;
 mod operator_argument_error;
+mod parse_error;
+mod reported_error;
 mod standard_error;
+mod tuple_arity_error;
+mod type_already_defined_error;
 mod type_mismatch_error;
+mod type_not_found_error;
 mod untyped_function_error;
+mod unused_function_warning;
 
 pub use crate::err::argument_error::ArgumentError;
+pub use crate::err::capital_name_error::CapitalNameError;
+pub use crate::err::case_missing_error::CaseMissingError;
+pub use crate::err::case_unreachable_error::CaseUnreachableError;
+pub use crate::err::compile_error::CompileError;
+pub use crate::err::constructor_not_found_error::ConstructorNotFoundError;
+pub use crate::err::diagnostics::Diagnostics;
 pub use crate::err::function_already_defined_error::FunctionAlreadyDefinedError;
 pub use crate::err::function_case_missing_error::FunctionCaseMissingError;
 pub use crate::err::function_case_unreachable_error::FunctionCaseUnreachableError;
 pub use crate::err::function_not_found_error::FunctionNotFoundError;
+pub use crate::err::list_element_error::ListElementError;
+pub use crate::err::location::Location;
 pub use crate::err::no_function_matches_error::NoFunctionMatchesError;
 pub use crate::err::not_implemented_error::NotImplementedError;
 pub use crate::err::operator_argument_error::OperatorArgumentError;
+pub use crate::err::parse_error::ParseError;
+pub use crate::err::reported_error::ReportedError;
 pub use crate::err::standard_error::StandardError;
+pub use crate::err::tuple_arity_error::TupleArityError;
+pub use crate::err::type_already_defined_error::TypeAlreadyDefinedError;
 pub use crate::err::type_mismatch_error::TypeMismatchError;
+pub use crate::err::type_not_found_error::TypeNotFoundError;
 pub use crate::err::untyped_function_error::UntypedFunctionError;
+pub use crate::err::unused_function_warning::UnusedFunctionWarning;
@@ -1,18 +1,31 @@
+use crate::err::{CompileError, Location};
+
 #[derive(Debug, Clone)]
 pub struct ArgumentError {
     pub function_name: String,
     pub expected: usize,
     pub actual: usize,
+    pub location: Location,
 }
 
 impl std::fmt::Display for ArgumentError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
             f,
-            "argument error: {} expected: {}, got: {}",
-            self.function_name, self.expected, self.actual
+            "{}: argument error: {} expected: {}, got: {}\n{}",
+            self.location,
+            self.function_name,
+            self.expected,
+            self.actual,
+            self.location.annotate()
         )
     }
 }
 
 impl std::error::Error for ArgumentError {}
+
+impl CompileError for ArgumentError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
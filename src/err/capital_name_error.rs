@@ -0,0 +1,27 @@
+use crate::err::{CompileError, Location};
+
+#[derive(Debug, Clone)]
+pub struct CapitalNameError {
+    pub name: String,
+    pub location: Location,
+}
+
+impl std::fmt::Display for CapitalNameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: capital name error: {}, only types and constructors start with a capital letter\n{}",
+            self.location,
+            self.name,
+            self.location.annotate()
+        )
+    }
+}
+
+impl std::error::Error for CapitalNameError {}
+
+impl CompileError for CapitalNameError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
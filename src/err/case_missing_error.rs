@@ -0,0 +1,25 @@
+use crate::err::{CompileError, Location};
+
+#[derive(Debug, Clone)]
+pub struct CaseMissingError {
+    pub location: Location,
+}
+
+impl std::fmt::Display for CaseMissingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: case missing error\n{}",
+            self.location,
+            self.location.annotate()
+        )
+    }
+}
+
+impl std::error::Error for CaseMissingError {}
+
+impl CompileError for CaseMissingError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
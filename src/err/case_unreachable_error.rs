@@ -0,0 +1,25 @@
+use crate::err::{CompileError, Location};
+
+#[derive(Debug, Clone)]
+pub struct CaseUnreachableError {
+    pub location: Location,
+}
+
+impl std::fmt::Display for CaseUnreachableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: case unreachable error\n{}",
+            self.location,
+            self.location.annotate()
+        )
+    }
+}
+
+impl std::error::Error for CaseUnreachableError {}
+
+impl CompileError for CaseUnreachableError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
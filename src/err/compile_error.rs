@@ -0,0 +1,12 @@
+use crate::err::Location;
+
+// An error or warning found while compiling, which the diagnostics order by its location
+pub trait CompileError: std::error::Error {
+    // where in the source it was found, if it is about any one place
+    fn location(&self) -> Option<&Location>;
+
+    // whether it stands in for an error that has already been added to the diagnostics
+    fn is_reported(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,27 @@
+use crate::err::{CompileError, Location};
+
+#[derive(Debug, Clone)]
+pub struct ConstructorNotFoundError {
+    pub name: String,
+    pub location: Location,
+}
+
+impl std::fmt::Display for ConstructorNotFoundError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: constructor not found error: {}\n{}",
+            self.location,
+            self.name,
+            self.location.annotate()
+        )
+    }
+}
+
+impl std::error::Error for ConstructorNotFoundError {}
+
+impl CompileError for ConstructorNotFoundError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
@@ -0,0 +1,92 @@
+use crate::err::{CompileError, StandardError};
+use std::error::Error;
+
+// Collects the errors and warnings found while compiling a file so they can all
+// be reported at once, ordered by where they occur in the source.
+#[derive(Debug)]
+pub struct Diagnostics {
+    pub file: String,
+    errors: Vec<Box<dyn CompileError>>,
+    warnings: Vec<Box<dyn CompileError>>,
+}
+
+impl Diagnostics {
+    pub fn new(file: &str) -> Self {
+        Self {
+            file: file.to_string(),
+            errors: vec![],
+            warnings: vec![],
+        }
+    }
+
+    pub fn error(&mut self, error: Box<dyn CompileError>) {
+        if !error.is_reported() {
+            self.errors.push(error);
+        }
+    }
+
+    pub fn warning(&mut self, warning: Box<dyn CompileError>) {
+        self.warnings.push(warning);
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.errors.len()
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.warnings.len()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    // records an error that stops compilation and returns the summary to bail out with
+    pub fn abort(&mut self, error: Box<dyn CompileError>) -> Box<dyn Error> {
+        self.error(error);
+        Box::new(StandardError { s: self.summary() })
+    }
+
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "could not compile {} due to {}",
+            self.file,
+            plural(self.error_count(), "error")
+        );
+        if !self.warnings.is_empty() {
+            summary = format!(
+                "{}; {} emitted",
+                summary,
+                plural(self.warning_count(), "warning")
+            );
+        }
+        summary
+    }
+}
+
+impl std::fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut entries: Vec<&Box<dyn CompileError>> =
+            self.errors.iter().chain(self.warnings.iter()).collect();
+        // errors without a location go last
+        entries.sort_by_key(|e| match e.location() {
+            Some(l) => (l.line, l.column),
+            None => (usize::MAX, usize::MAX),
+        });
+
+        for entry in entries {
+            match entry.location() {
+                Some(_) => writeln!(f, "{}:{}", self.file, entry)?,
+                None => writeln!(f, "{}: {}", self.file, entry)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("{} {}", count, noun),
+        _ => format!("{} {}s", count, noun),
+    }
+}
@@ -1,12 +1,27 @@
+use crate::err::{CompileError, Location};
+
 #[derive(Debug, Clone)]
 pub struct FunctionAlreadyDefinedError {
     pub function_name: String,
+    pub location: Location,
 }
 
 impl std::fmt::Display for FunctionAlreadyDefinedError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "function already defined error: {}", self.function_name)
+        write!(
+            f,
+            "{}: function already defined error: {}\n{}",
+            self.location,
+            self.function_name,
+            self.location.annotate()
+        )
     }
 }
 
 impl std::error::Error for FunctionAlreadyDefinedError {}
+
+impl CompileError for FunctionAlreadyDefinedError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
@@ -1,12 +1,27 @@
+use crate::err::{CompileError, Location};
+
 #[derive(Debug, Clone)]
 pub struct FunctionCaseMissingError {
     pub function_name: String,
+    pub location: Location,
 }
 
 impl std::fmt::Display for FunctionCaseMissingError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "function case missing error: {}", self.function_name)
+        write!(
+            f,
+            "{}: function case missing error: {}\n{}",
+            self.location,
+            self.function_name,
+            self.location.annotate()
+        )
     }
 }
 
 impl std::error::Error for FunctionCaseMissingError {}
+
+impl CompileError for FunctionCaseMissingError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
@@ -1,12 +1,27 @@
+use crate::err::{CompileError, Location};
+
 #[derive(Debug, Clone)]
 pub struct FunctionNotFoundError {
     pub name: String,
+    pub location: Location,
 }
 
 impl std::fmt::Display for FunctionNotFoundError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "function not found error: {}", self.name)
+        write!(
+            f,
+            "{}: function not found error: {}\n{}",
+            self.location,
+            self.name,
+            self.location.annotate()
+        )
     }
 }
 
 impl std::error::Error for FunctionNotFoundError {}
+
+impl CompileError for FunctionNotFoundError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
@@ -0,0 +1,30 @@
+use crate::ast::VariableType;
+use crate::err::{CompileError, Location};
+
+#[derive(Debug, Clone)]
+pub struct ListElementError {
+    pub expected: VariableType,
+    pub got: VariableType,
+    pub location: Location,
+}
+
+impl std::fmt::Display for ListElementError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: every element of a list must have the same type, expected: {}, got: {}\n{}",
+            self.location,
+            self.expected,
+            self.got,
+            self.location.annotate()
+        )
+    }
+}
+
+impl std::error::Error for ListElementError {}
+
+impl CompileError for ListElementError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
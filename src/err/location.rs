@@ -0,0 +1,99 @@
+use crate::parser::{BLOCK_END, SEPARATOR};
+use pest::Span;
+
+#[derive(Debug, Clone)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+    pub source_line: String,
+    pub length: usize,
+}
+
+impl Location {
+    pub fn new(span: &Span) -> Self {
+        // the marks added by the layout pass aren't part of the source as it was written
+        let is_source = |c: &char| !matches!(*c, SEPARATOR | BLOCK_END);
+
+        let (line, column) = span.start_pos().line_col();
+        let line_of = span.start_pos().line_of();
+        let column = column
+            - line_of
+                .chars()
+                .take(column - 1)
+                .filter(|c| !is_source(c))
+                .count();
+        let source_line: String = line_of
+            .trim_end_matches(&['\n', '\r'][..])
+            .chars()
+            .filter(is_source)
+            .collect();
+
+        // spans covering several lines are underlined up to the end of the first one
+        let remaining = source_line.chars().count() + 1 - column;
+        let length = span
+            .as_str()
+            .lines()
+            .next()
+            .unwrap_or("")
+            .chars()
+            .filter(is_source)
+            .count();
+
+        Self {
+            line,
+            column,
+            source_line,
+            length: length.min(remaining).max(1),
+        }
+    }
+
+    pub fn annotate(&self) -> String {
+        let gutter = " ".repeat(self.line.to_string().len());
+        let padding: String = self
+            .source_line
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        format!(
+            "{} |\n{} | {}\n{} | {}{}",
+            gutter,
+            self.line,
+            self.source_line,
+            gutter,
+            padding,
+            "^".repeat(self.length)
+        )
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location() {
+        let source = "five = 5\nmain = 1 + true\n";
+        let location = Location::new(&Span::new(source, 20, 24).unwrap());
+        assert_eq!(location.to_string(), "2:12");
+        assert_eq!(
+            location.annotate(),
+            "  |\n2 | main = 1 + true\n  |            ^^^^"
+        );
+    }
+
+    #[test]
+    fn test_multiline_location() {
+        let source = "main = (1 +\n2)\n";
+        let location = Location::new(&Span::new(source, 7, 14).unwrap());
+        assert_eq!(location.to_string(), "1:8");
+        assert_eq!(location.annotate(), "  |\n1 | main = (1 +\n  |        ^^^^");
+    }
+}
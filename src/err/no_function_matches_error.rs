@@ -1,12 +1,27 @@
+use crate::err::{CompileError, Location};
+
 #[derive(Debug, Clone)]
 pub struct NoFunctionMatchesError {
     pub name: String,
+    pub location: Location,
 }
 
 impl std::fmt::Display for NoFunctionMatchesError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "no match for function error: {}", self.name)
+        write!(
+            f,
+            "{}: no match for function error: {}\n{}",
+            self.location,
+            self.name,
+            self.location.annotate()
+        )
     }
 }
 
 impl std::error::Error for NoFunctionMatchesError {}
+
+impl CompileError for NoFunctionMatchesError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
@@ -1,12 +1,27 @@
+use crate::err::{CompileError, Location};
+
 #[derive(Debug, Clone)]
 pub struct NotImplementedError {
     pub sub: String,
+    pub location: Location,
 }
 
 impl std::fmt::Display for NotImplementedError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "not implemented error: {}", self.sub)
+        write!(
+            f,
+            "{}: not implemented error: {}\n{}",
+            self.location,
+            self.sub,
+            self.location.annotate()
+        )
     }
 }
 
 impl std::error::Error for NotImplementedError {}
+
+impl CompileError for NotImplementedError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
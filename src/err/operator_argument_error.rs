@@ -1,19 +1,30 @@
-use crate::ast::{Operator, VariableType};
+use crate::ast::VariableType;
+use crate::err::{CompileError, Location};
 
 #[derive(Debug, Clone)]
 pub struct OperatorArgumentError {
-    pub operator: Operator,
+    pub operator: String,
     pub argument_type: VariableType,
+    pub location: Location,
 }
 
 impl std::fmt::Display for OperatorArgumentError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
             f,
-            "no implementation of {} for {}",
-            self.operator, self.argument_type
+            "{}: no implementation of '{}' for {}\n{}",
+            self.location,
+            self.operator,
+            self.argument_type,
+            self.location.annotate()
         )
     }
 }
 
 impl std::error::Error for OperatorArgumentError {}
+
+impl CompileError for OperatorArgumentError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
@@ -0,0 +1,27 @@
+use crate::err::{CompileError, Location};
+
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub message: String,
+    pub location: Location,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: parse error: {}\n{}",
+            self.location,
+            self.message,
+            self.location.annotate()
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl CompileError for ParseError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
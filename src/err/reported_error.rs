@@ -0,0 +1,24 @@
+use crate::err::{CompileError, Location};
+
+// Returned in place of an error that has already been added to the diagnostics,
+// so callers stop checking without reporting it a second time.
+#[derive(Debug, Clone)]
+pub struct ReportedError;
+
+impl std::fmt::Display for ReportedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "error already reported")
+    }
+}
+
+impl std::error::Error for ReportedError {}
+
+impl CompileError for ReportedError {
+    fn location(&self) -> Option<&Location> {
+        None
+    }
+
+    fn is_reported(&self) -> bool {
+        true
+    }
+}
@@ -1,3 +1,5 @@
+use crate::err::{CompileError, Location};
+
 #[derive(Debug, Clone)]
 pub struct StandardError {
     pub s: String,
@@ -10,3 +12,9 @@ impl std::fmt::Display for StandardError {
 }
 
 impl std::error::Error for StandardError {}
+
+impl CompileError for StandardError {
+    fn location(&self) -> Option<&Location> {
+        None
+    }
+}
@@ -0,0 +1,32 @@
+use crate::ast::VariableType;
+use crate::err::{CompileError, Location};
+
+#[derive(Debug, Clone)]
+pub struct TupleArityError {
+    pub expected: VariableType,
+    pub got: VariableType,
+    pub location: Location,
+}
+
+impl std::fmt::Display for TupleArityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: tuple arity mismatch error, expected {} elements: {}, got {}: {}\n{}",
+            self.location,
+            self.expected.arity().unwrap_or(1),
+            self.expected,
+            self.got.arity().unwrap_or(1),
+            self.got,
+            self.location.annotate()
+        )
+    }
+}
+
+impl std::error::Error for TupleArityError {}
+
+impl CompileError for TupleArityError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
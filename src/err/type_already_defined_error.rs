@@ -0,0 +1,27 @@
+use crate::err::{CompileError, Location};
+
+#[derive(Debug, Clone)]
+pub struct TypeAlreadyDefinedError {
+    pub name: String,
+    pub location: Location,
+}
+
+impl std::fmt::Display for TypeAlreadyDefinedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: type already defined error: {}\n{}",
+            self.location,
+            self.name,
+            self.location.annotate()
+        )
+    }
+}
+
+impl std::error::Error for TypeAlreadyDefinedError {}
+
+impl CompileError for TypeAlreadyDefinedError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
@@ -1,19 +1,30 @@
 use crate::ast::VariableType;
+use crate::err::{CompileError, Location};
 
 #[derive(Debug, Clone)]
 pub struct TypeMismatchError {
     pub expected: VariableType,
     pub got: VariableType,
+    pub location: Location,
 }
 
 impl std::fmt::Display for TypeMismatchError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
             f,
-            "type mismatch error, expected: {}, got: {}",
-            self.expected, self.got
+            "{}: type mismatch error, expected: {}, got: {}\n{}",
+            self.location,
+            self.expected,
+            self.got,
+            self.location.annotate()
         )
     }
 }
 
 impl std::error::Error for TypeMismatchError {}
+
+impl CompileError for TypeMismatchError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
@@ -0,0 +1,27 @@
+use crate::err::{CompileError, Location};
+
+#[derive(Debug, Clone)]
+pub struct TypeNotFoundError {
+    pub name: String,
+    pub location: Location,
+}
+
+impl std::fmt::Display for TypeNotFoundError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: type not found error: {}\n{}",
+            self.location,
+            self.name,
+            self.location.annotate()
+        )
+    }
+}
+
+impl std::error::Error for TypeNotFoundError {}
+
+impl CompileError for TypeNotFoundError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
@@ -1,16 +1,27 @@
+use crate::err::{CompileError, Location};
+
 #[derive(Debug, Clone)]
 pub struct UntypedFunctionError {
     pub function_name: String,
+    pub location: Location,
 }
 
 impl std::fmt::Display for UntypedFunctionError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
             f,
-            "untyped function error: could not determine type for function {}",
-            self.function_name
+            "{}: untyped function error: could not determine type for function {}\n{}",
+            self.location,
+            self.function_name,
+            self.location.annotate()
         )
     }
 }
 
 impl std::error::Error for UntypedFunctionError {}
+
+impl CompileError for UntypedFunctionError {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
@@ -0,0 +1,27 @@
+use crate::err::{CompileError, Location};
+
+#[derive(Debug, Clone)]
+pub struct UnusedFunctionWarning {
+    pub function_name: String,
+    pub location: Location,
+}
+
+impl std::fmt::Display for UnusedFunctionWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: warning: unused function {}\n{}",
+            self.location,
+            self.function_name,
+            self.location.annotate()
+        )
+    }
+}
+
+impl std::error::Error for UnusedFunctionWarning {}
+
+impl CompileError for UnusedFunctionWarning {
+    fn location(&self) -> Option<&Location> {
+        Some(&self.location)
+    }
+}
@@ -1,3 +1,361 @@
+use crate::err::{Location, ParseError};
+use pest::error::{Error, ErrorVariant, InputLocation};
+use pest::iterators::Pairs;
+use pest::Span;
+
+mod layout;
+
+pub use crate::parser::layout::{layout, tokenize, BLOCK_END, SEPARATOR};
+
 #[derive(Parser)]
 #[grammar = "parser/muru.pest"]
 pub struct Parser;
+
+pub fn parse(source: &str) -> Result<Pairs<'_, Rule>, ParseError> {
+    let pairs = match <Parser as pest::Parser<Rule>>::parse(Rule::program, source) {
+        Ok(pairs) => pairs,
+        Err(e) => return Err(describe(source, e)),
+    };
+    check_literals(pairs.clone())?;
+    Ok(pairs)
+}
+
+// pest can't turn every literal it accepts into a number, so catch those before building the ast
+fn check_literals(pairs: Pairs<Rule>) -> Result<(), ParseError> {
+    for pair in pairs {
+        let text = pair.as_str();
+        let message = match pair.as_rule() {
+            Rule::int if text.parse::<i32>().is_ok() => None,
+            Rule::int if text.parse::<i64>().is_ok() => {
+                Some("integer literal is too large for an int, add an 'L' to make it an int64")
+            }
+            Rule::int => Some("integer literal is too large"),
+            Rule::int64 if text.trim_end_matches('L').parse::<i64>().is_ok() => None,
+            Rule::int64 => Some("integer literal is too large"),
+            Rule::float if is_finite::<f32>(text) => None,
+            Rule::float if is_finite::<f64>(text) => {
+                Some("float literal is too large for a float, add a 'd' to make it a double")
+            }
+            Rule::float => Some("float literal is too large"),
+            Rule::double if is_finite::<f64>(text.trim_end_matches('d')) => None,
+            Rule::double => Some("float literal is too large"),
+            _ => {
+                check_literals(pair.into_inner())?;
+                continue;
+            }
+        };
+        if let Some(message) = message {
+            return Err(ParseError {
+                message: message.to_string(),
+                location: Location::new(&pair.as_span()),
+            });
+        }
+    }
+    Ok(())
+}
+
+// floats too large for their type parse as infinity rather than failing
+fn is_finite<F: std::str::FromStr + Into<f64>>(text: &str) -> bool {
+    match text.parse::<F>() {
+        Ok(f) => f.into().is_finite(),
+        Err(_) => false,
+    }
+}
+
+// Turns a pest error into a message about the muru source rather than the grammar rules.
+fn describe(source: &str, e: Error<Rule>) -> ParseError {
+    let pos = match e.location {
+        InputLocation::Pos(pos) => pos,
+        InputLocation::Span((start, _)) => start,
+    };
+    let positives = match e.variant {
+        ErrorVariant::ParsingError { positives, .. } => positives,
+        ErrorVariant::CustomError { .. } => vec![],
+    };
+
+    let line_start = source[..pos].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[pos..].find('\n').map_or(source.len(), |i| pos + i);
+
+    let at = |offset: usize, message: String| ParseError {
+        message,
+        location: Location::new(&Span::new(source, offset, offset).unwrap()),
+    };
+
+    // definitions start in the first column, and brackets can be left open across the indented
+    // lines continuing them
+    let mut definition_start = line_start;
+    while definition_start > 0
+        && !source[definition_start..].starts_with(|c: char| !c.is_whitespace() && c != '#')
+    {
+        definition_start = source[..definition_start - 1]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+    }
+
+    // the tokens leave out comments and keep char and string literals whole, so a bracket or '#'
+    // inside a literal is just part of it
+    let tokens = tokenize(&source[definition_start..line_end]);
+
+    // pest only tracks rules, so a missing ")" or "]" shows up somewhere inside the brackets
+    let mut opened = vec![];
+    for token in &tokens {
+        let offset = definition_start + token.offset;
+        let name = match token.text {
+            "(" | ")" => "parenthesis",
+            "[" | "]" => "bracket",
+            _ => continue,
+        };
+        match token.text {
+            "(" | "[" => opened.push((offset, token.text, name)),
+            close => match opened.pop() {
+                Some((_, "(", _)) if close == ")" => {}
+                Some((_, "[", _)) if close == "]" => {}
+                _ => return at(offset, format!("unmatched closing {}", name)),
+            },
+        }
+    }
+    if let Some((open, _, name)) = opened.last() {
+        return at(*open, format!("unbalanced {} opened here", name));
+    }
+
+    // the layout pass starts a new binding on lines that line up with the bindings above
+    if source[line_start..pos]
+        .trim_end_matches(&[' ', '\t'][..])
+        .ends_with(SEPARATOR)
+    {
+        return at(
+            pos,
+            "expected a binding, as this line lines up with the bindings above it".to_string(),
+        );
+    }
+
+    let before = source[line_start..pos].trim_end();
+    let expected = if positives.contains(&Rule::var_type) {
+        "a type"
+    } else if positives.contains(&Rule::type_name) {
+        "a constructor"
+    } else {
+        "an expression"
+    };
+    for token in &[
+        "::", "->", "==", "!=", "&&", "||", "!", "<<", ">>", "<=", ">=", "=", "?", ":", "+", "-",
+        "*", "/", "%", "&", "|", "^", "<", ">",
+    ] {
+        if before.ends_with(token) {
+            return at(pos, format!("expected {} after '{}'", expected, token));
+        }
+    }
+
+    // the code on the line ends with its last token, before any comment
+    let code_end = tokens
+        .last()
+        .map_or(line_start, |t| definition_start + t.offset + t.text.len());
+    let rest = source.get(pos..code_end).unwrap_or("");
+    let offset = pos + (rest.len() - rest.trim_start().len());
+    let rest = rest.trim_start();
+    match rest.chars().next() {
+        Some('?') if rest.contains(':') => at(
+            offset,
+            "the condition of a ternary must be a single value, wrap it in parentheses".to_string(),
+        ),
+        Some('?') => at(offset, "expected ':' after the '?' branch".to_string()),
+        Some(c) => at(offset, format!("unexpected '{}'", c)),
+        None => at(pos, format!("expected {}", describe_rules(&positives))),
+    }
+}
+
+fn describe_rules(rules: &[Rule]) -> String {
+    let mut descriptions: Vec<&str> = vec![];
+    for rule in rules {
+        let description = match rule {
+            Rule::parameter
+            | Rule::empty_list
+            | Rule::cons_pattern
+            | Rule::tuple_pattern
+            | Rule::constructor_pattern
+            | Rule::guard => "a parameter or '='",
+            Rule::alternative => "a pattern",
+            Rule::variable => "a name",
+            Rule::var_type
+            | Rule::list_type
+            | Rule::tuple_type
+            | Rule::int_type
+            | Rule::int64_type
+            | Rule::float_type
+            | Rule::double_type
+            | Rule::bool_type
+            | Rule::char_type
+            | Rule::string_type
+            | Rule::unit_type => "a type",
+            Rule::type_name => "a type name",
+            Rule::operator => "an operator",
+            Rule::literal
+            | Rule::int
+            | Rule::int64
+            | Rule::float
+            | Rule::double
+            | Rule::bool
+            | Rule::char
+            | Rule::string
+            | Rule::unit => "a value",
+            Rule::EOI | Rule::line => "the end of the line",
+            Rule::program | Rule::function | Rule::function_signature | Rule::data => {
+                "a function or type signature"
+            }
+            _ => "an expression",
+        };
+        if !descriptions.contains(&description) {
+            descriptions.push(description);
+        }
+    }
+
+    match descriptions.split_last() {
+        None => "something else".to_string(),
+        Some((last, [])) => last.to_string(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(source: &str) -> String {
+        match parse(source) {
+            Ok(_) => panic!("expected {:?} to fail to parse", source),
+            Err(e) => e.to_string().lines().next().unwrap().to_string(),
+        }
+    }
+
+    #[test]
+    fn test_missing_expression() {
+        assert_eq!(
+            parse_error("main = \n"),
+            "1:8: parse error: expected an expression after '='"
+        );
+        assert_eq!(
+            parse_error("main = 1 +\n"),
+            "1:11: parse error: expected an expression after '+'"
+        );
+    }
+
+    #[test]
+    fn test_missing_type() {
+        assert_eq!(
+            parse_error("f :: int ->\n"),
+            "1:12: parse error: expected a type after '->'"
+        );
+        assert_eq!(
+            parse_error("f ::\n"),
+            "1:5: parse error: expected a type after '::'"
+        );
+    }
+
+    #[test]
+    fn test_parenthesis() {
+        assert_eq!(
+            parse_error("main = f (g (1 + 2)\n"),
+            "1:10: parse error: unbalanced parenthesis opened here"
+        );
+        assert_eq!(
+            parse_error("main = 1 + 2)\n"),
+            "1:13: parse error: unmatched closing parenthesis"
+        );
+        assert_eq!(
+            parse_error("main = [1, (2]\n"),
+            "1:14: parse error: unmatched closing bracket"
+        );
+        assert_eq!(
+            parse_error("main = f [1, 2\n"),
+            "1:10: parse error: unbalanced bracket opened here"
+        );
+        assert_eq!(
+            parse_error("main = f\n  (1 +\n   2\n"),
+            "2:3: parse error: unbalanced parenthesis opened here"
+        );
+        assert_eq!(
+            parse_error("f = 1\nmain = [f,\n\n  # (\n    f\n"),
+            "2:8: parse error: unbalanced bracket opened here"
+        );
+    }
+
+    #[test]
+    fn test_unexpected() {
+        assert_eq!(
+            parse_error("main = 1 $ 2\n"),
+            "1:10: parse error: unexpected '$'"
+        );
+        assert_eq!(
+            parse_error("main = true ? 1\n"),
+            "1:13: parse error: expected ':' after the '?' branch"
+        );
+        assert_eq!(
+            parse_error("main = '#' $ 1\n"),
+            "1:12: parse error: unexpected '$'"
+        );
+        assert_eq!(
+            parse_error("main = prints \":)\" +\n"),
+            "1:21: parse error: expected an expression after '+'"
+        );
+        assert_eq!(
+            parse_error("main = prints \"(#\" +\n"),
+            "1:21: parse error: expected an expression after '+'"
+        );
+        assert_eq!(
+            parse_error("main 1 2\n"),
+            "1:9: parse error: expected a parameter or '='"
+        );
+        assert_eq!(
+            parse_error("main = 1 + 2 == 3 ? 4 : 5\n"),
+            "1:19: parse error: the condition of a ternary must be a single value, wrap it in parentheses"
+        );
+    }
+
+    #[test]
+    fn test_data() {
+        assert_eq!(
+            parse_error("data Shape = Circle float |\n"),
+            "1:28: parse error: expected a constructor after '|'"
+        );
+        assert_eq!(
+            parse_error("data Shape =\n"),
+            "1:13: parse error: expected a constructor after '='"
+        );
+        assert_eq!(
+            parse_error("data\n"),
+            "1:5: parse error: expected a type name"
+        );
+    }
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(
+            parse_error(&layout("main = g where g = 1\n               + 2\n").unwrap()),
+            "2:16: parse error: expected a binding, as this line lines up with the bindings above it"
+        );
+    }
+
+    #[test]
+    fn test_literal_too_large() {
+        assert_eq!(
+            parse_error("main = 99999999999999999999\n"),
+            "1:8: parse error: integer literal is too large"
+        );
+        assert_eq!(
+            parse_error("main = 9999999999L + 99999999999999999999L\n"),
+            "1:22: parse error: integer literal is too large"
+        );
+        assert_eq!(
+            parse_error("main = f 2147483647 2147483648\n"),
+            "1:21: parse error: integer literal is too large for an int, add an 'L' to make it an int64"
+        );
+        assert_eq!(
+            parse_error(&format!("main = {}.0\n", "9".repeat(40))),
+            "1:8: parse error: float literal is too large for a float, add a 'd' to make it a double"
+        );
+        assert_eq!(
+            parse_error(&format!("main = {}.0d\n", "9".repeat(400))),
+            "1:8: parse error: float literal is too large"
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use crate::err::{Location, ParseError};
+use pest::Span;
+
+// Marks the start of a new entry in a block, inserted just before it. Error locations leave the
+// marks out, so they still point at the source as it was written.
+pub const SEPARATOR: char = '\u{1f}';
+// Marks the end of a block that is closed by a line indented less than its entries, so a line
+// lining up with an enclosing block continues that block rather than the nested one.
+pub const BLOCK_END: char = '\u{1e}';
+
+pub struct Token<'s> {
+    pub text: &'s str,
+    pub offset: usize,
+    line: usize,
+    column: usize,
+}
+
+#[derive(PartialEq)]
+enum BlockKind {
+    Let,
+    Where,
+    Of,
+}
+
+// a block of bindings opened by `let` or `where`, or of alternatives opened by `of`, lined up on
+// the column of its first entry
+struct Block {
+    kind: BlockKind,
+    column: usize,
+    first: usize,
+}
+
+// Definitions start in the first column and any indented line continues the one above it. Inside
+// `let` and `where` blocks a line lined up with the first binding starts another binding, as if
+// it was separated with `;`, and likewise for the alternatives of a `case ... of`.
+pub fn layout(source: &str) -> Result<String, ParseError> {
+    let tokens = tokenize(source);
+    let mut marks: Vec<(usize, String)> = vec![];
+    let mut blocks: Vec<Block> = vec![];
+    let mut indentation: Option<char> = None;
+
+    let at = |offset: usize, message: &str| ParseError {
+        message: message.to_string(),
+        location: Location::new(&Span::new(source, offset, offset).unwrap()),
+    };
+
+    for (i, token) in tokens.iter().enumerate() {
+        let starts_line = i == 0 || tokens[i - 1].line != token.line;
+        if starts_line && token.column == 1 {
+            blocks.clear();
+        } else if starts_line {
+            if i == 0 {
+                return Err(at(
+                    token.offset,
+                    "indented line does not continue a definition",
+                ));
+            }
+
+            let line_start = token.offset - (token.column - 1);
+            for c in source[line_start..token.offset].chars() {
+                if *indentation.get_or_insert(c) != c {
+                    return Err(at(line_start, "indentation mixes tabs and spaces"));
+                }
+            }
+
+            // a function's `where` can't be part of any block, and `in` closes its own block below
+            let mut mark = String::new();
+            while let Some(block) = blocks.last() {
+                if token.text != "where"
+                    && (block.column <= token.column
+                        || (block.kind == BlockKind::Let && token.text == "in"))
+                {
+                    break;
+                }
+                blocks.pop();
+                mark.push(BLOCK_END);
+            }
+            let dedented = !mark.is_empty();
+
+            match blocks.last() {
+                Some(block) if block.column == token.column && block.first != token.offset => {
+                    mark.push(SEPARATOR);
+                }
+                _ if dedented && token.text != "in" && token.text != "where" => {
+                    return Err(at(
+                        token.offset,
+                        "this line is indented less than the bindings above it, but does not line up with an enclosing block",
+                    ));
+                }
+                _ => {}
+            }
+            if !mark.is_empty() {
+                marks.push((token.offset, mark));
+            }
+        }
+
+        match token.text {
+            "let" | "where" | "of" => {
+                if let Some(first) = tokens.get(i + 1) {
+                    blocks.push(Block {
+                        kind: match token.text {
+                            "let" => BlockKind::Let,
+                            "where" => BlockKind::Where,
+                            _ => BlockKind::Of,
+                        },
+                        column: first.column,
+                        first: first.offset,
+                    });
+                }
+            }
+            "in" => {
+                if let Some(i) = blocks.iter().rposition(|b| b.kind == BlockKind::Let) {
+                    blocks.truncate(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut source = source.to_string();
+    for (offset, mark) in marks.iter().rev() {
+        source.insert_str(*offset, mark);
+    }
+    Ok(source)
+}
+
+// Splits the source into words, literals and symbols, leaving out whitespace and comments
+pub fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut line_start = 0;
+    let mut chars = source.char_indices().peekable();
+
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '\n' => {
+                line += 1;
+                line_start = offset + 1;
+            }
+            '#' => while chars.next_if(|&(_, c)| c != '\n').is_some() {},
+            c if c.is_whitespace() => {}
+            _ => {
+                let mut end = offset + c.len_utf8();
+                // a char or string literal is one token, whatever it contains
+                if c == '\'' || c == '"' {
+                    let mut escaped = false;
+                    while let Some((i, next)) = chars.next_if(|&(_, c)| c != '\n') {
+                        end = i + next.len_utf8();
+                        if next == c && !escaped {
+                            break;
+                        }
+                        escaped = next == '\\' && !escaped;
+                    }
+                } else if c.is_ascii_alphanumeric() || c == '_' {
+                    while let Some((i, c)) =
+                        chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '_')
+                    {
+                        end = i + c.len_utf8();
+                    }
+                }
+                tokens.push(Token {
+                    text: &source[offset..end],
+                    offset,
+                    line,
+                    column: source[line_start..offset].chars().count() + 1,
+                });
+            }
+        }
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout_error(source: &str) -> String {
+        match layout(source) {
+            Ok(_) => panic!("expected {:?} to be rejected", source),
+            Err(e) => e.to_string().lines().next().unwrap().to_string(),
+        }
+    }
+
+    #[test]
+    fn test_continuation() {
+        let source = "main = 1\n  + 2 # comment\n\n    + 3\nf = 4\n";
+        assert_eq!(layout(source).unwrap(), source);
+    }
+
+    #[test]
+    fn test_blocks() {
+        let source = "f x = a + b\n  where a = 1\n        b = let c = 2\n                d = 3\n            in c + d\n";
+        assert_eq!(
+            layout(source).unwrap(),
+            "f x = a + b\n  where a = 1\n        \u{1f}b = let c = 2\n                \u{1f}d = 3\n            in c + d\n"
+        );
+
+        let source = "f x = y\n  where\n    y = x\n    z = y\n";
+        assert_eq!(
+            layout(source).unwrap(),
+            "f x = y\n  where\n    y = x\n    \u{1f}z = y\n"
+        );
+
+        let source =
+            "f x = case x of\n  1 -> case y of\n    2 -> 3\n    _ -> 4\n  _ -> 5\n  where y = 2\n";
+        assert_eq!(
+            layout(source).unwrap(),
+            "f x = case x of\n  1 -> case y of\n    2 -> 3\n    \u{1f}_ -> 4\n  \u{1e}\u{1f}_ -> 5\n  \u{1e}where y = 2\n"
+        );
+
+        let source = "f x = y\n  where y = \"\\\" where # let\"\n        z = '#'\n";
+        assert_eq!(
+            layout(source).unwrap(),
+            "f x = y\n  where y = \"\\\" where # let\"\n        \u{1f}z = '#'\n"
+        );
+    }
+
+    #[test]
+    fn test_ambiguous() {
+        assert_eq!(
+            layout_error("  main = 1\n"),
+            "1:3: parse error: indented line does not continue a definition"
+        );
+        assert_eq!(
+            layout_error("main = 1\n  + 2\n\t+ 3\n"),
+            "3:1: parse error: indentation mixes tabs and spaces"
+        );
+        assert_eq!(
+            layout_error("f = a\n  where a = 1\n        b = 2\n    + 3\n"),
+            "4:5: parse error: this line is indented less than the bindings above it, but does not line up with an enclosing block"
+        );
+    }
+}
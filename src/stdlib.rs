@@ -1,10 +1,27 @@
+mod convert;
+mod heap;
+mod lists;
+mod memory;
+mod printb;
 mod printc;
+mod printd;
 mod printi;
+mod printl;
+mod prints;
+mod tuples;
 
 use crate::ast::FunctionSignature;
 use crate::wasm::Expression;
 use std::collections::HashMap;
 
+pub use crate::stdlib::heap::layout as heap_layout;
+pub use crate::stdlib::heap::{keep, pop_frame, push_frame};
+pub use crate::stdlib::lists::{cons, head, tail};
+pub use crate::stdlib::memory::DATA_START;
+pub use crate::stdlib::printd::{DOUBLE_SCALE, FLOAT_SCALE};
+pub use crate::stdlib::tuples::MAX_ELEMENTS as MAX_TUPLE_ELEMENTS;
+pub use crate::stdlib::tuples::{field, set_field, tuple};
+
 pub struct Func {
     pub sig: FunctionSignature,
     pub wasm: Expression,
@@ -12,13 +29,33 @@ pub struct Func {
 
 pub struct Lib<'a> {
     pub funcs: HashMap<&'a str, Func>,
+    // functions the library is built from, which can't be called from muru
+    pub helpers: Vec<Expression>,
 }
 
 impl<'a> Lib<'a> {
     pub fn new() -> Self {
         let mut funcs = HashMap::new();
         funcs.insert("printi", printi::new());
+        funcs.insert("printl", printl::new());
+        funcs.insert("printb", printb::new());
         funcs.insert("printc", printc::new());
-        Self { funcs: funcs }
+        funcs.insert("prints", prints::new());
+        for (name, func) in printd::new().into_iter().chain(convert::new()) {
+            funcs.insert(name, func);
+        }
+
+        let mut helpers = vec![
+            memory::helper(),
+            printi::helper(),
+            printl::helper(),
+            printb::helper(),
+            printc::helper(),
+            printd::helper(),
+        ];
+        helpers.extend(heap::helpers());
+        helpers.extend(lists::helpers());
+
+        Self { funcs, helpers }
     }
 }
@@ -0,0 +1,95 @@
+use super::Func;
+use crate::ast::{FunctionSignature, VariableType};
+use crate::wasm::Expression;
+use crate::{wasm, wasm_dollar};
+
+// a function converting its one argument from one type to another
+fn conversion(name: &str, from: VariableType, to: VariableType, body: Expression) -> Func {
+    Func {
+        sig: FunctionSignature {
+            arg_types: vec![from.clone()],
+            return_type: to.clone(),
+        },
+        wasm: wasm!(
+            "func",
+            wasm_dollar!(name),
+            wasm!("param", wasm_dollar!("value"), from.to_wasm()),
+            wasm!("result", to.to_wasm()),
+            body
+        ),
+    }
+}
+
+fn value() -> Expression {
+    wasm!("local.get", wasm_dollar!("value"))
+}
+
+// Truncates towards zero. Floats beyond the range of an int are clamped to the nearest int
+// rather than trapping, and NaN is 0.
+fn to_int() -> Expression {
+    wasm!(
+        "if",
+        wasm!("result", "i32"),
+        wasm!("f32.ne", value(), value()),
+        wasm!("then", wasm!("i32.const", 0)),
+        wasm!(
+            "else",
+            wasm!(
+                "if",
+                wasm!("result", "i32"),
+                wasm!("f32.ge", value(), wasm!("f32.const", 2147483648.0f32)),
+                wasm!("then", wasm!("i32.const", i32::MAX)),
+                wasm!(
+                    "else",
+                    wasm!(
+                        "if",
+                        wasm!("result", "i32"),
+                        wasm!("f32.lt", value(), wasm!("f32.const", -2147483648.0f32)),
+                        wasm!("then", wasm!("i32.const", i32::MIN)),
+                        wasm!("else", wasm!("i32.trunc_f32_s", value()))
+                    )
+                )
+            )
+        )
+    )
+}
+
+pub fn new() -> Vec<(&'static str, Func)> {
+    use VariableType::*;
+
+    vec![
+        (
+            "toFloat",
+            conversion("toFloat", Int, Float, wasm!("f32.convert_i32_s", value())),
+        ),
+        ("toInt", conversion("toInt", Float, Int, to_int())),
+        // true is 1 and false is 0, which is how bools are already stored
+        ("fromBool", conversion("fromBool", Bool, Int, value())),
+        // chars are stored as their code point
+        ("toChar", conversion("toChar", Int, Char, value())),
+        ("fromChar", conversion("fromChar", Char, Int, value())),
+        (
+            "toInt64",
+            conversion("toInt64", Int, Int64, wasm!("i64.extend_i32_s", value())),
+        ),
+        // keeps the low 32 bits
+        (
+            "fromInt64",
+            conversion("fromInt64", Int64, Int, wasm!("i32.wrap_i64", value())),
+        ),
+        (
+            "toDouble",
+            conversion("toDouble", Float, Double, wasm!("f64.promote_f32", value())),
+        ),
+        // rounds to the nearest float
+        (
+            "fromDouble",
+            conversion(
+                "fromDouble",
+                Double,
+                Float,
+                wasm!("f32.demote_f64", value()),
+            ),
+        ),
+    ]
+}
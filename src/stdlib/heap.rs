@@ -0,0 +1,755 @@
+use crate::wasm::Expression;
+use crate::{wasm, wasm_dollar};
+
+// The heap follows the static data and the shadow stack, and grows up through memory one block
+// at a time. Each block starts with a header of two words. The first is the size of the block,
+// header included, with the lowest bit set while it is allocated and the next one set while a
+// collection has found it to be in use. In free blocks the second word is the next free block,
+// and in allocated ones it has a bit set for each 8 byte field that holds a heap address.
+const HEADER: i32 = 8;
+const ALLOCATED: i32 = 1;
+const MARKED: i32 = 2;
+// the smallest block worth splitting off the end of a free block
+const MIN_BLOCK: i32 = 16;
+const PAGE_BITS: i32 = 16;
+
+// Every heap address that code still needs is kept in a slot on the shadow stack, so it can be
+// found by the collector.
+const STACK_SIZE: usize = 256 * 1024;
+// a collection runs once twice as much as was in use after the last one has been allocated, or
+// this much if that is more
+const MIN_THRESHOLD: i32 = 1024 * 1024;
+
+fn get(name: &str) -> Expression {
+    wasm!("local.get", wasm_dollar!(name))
+}
+
+fn set(name: &str, value: Expression) -> Expression {
+    wasm!("local.set", wasm_dollar!(name), value)
+}
+
+fn global(name: &str) -> Expression {
+    wasm!("global.get", wasm_dollar!(name))
+}
+
+fn set_global(name: &str, value: Expression) -> Expression {
+    wasm!("global.set", wasm_dollar!(name), value)
+}
+
+fn add(name: &str, value: Expression) -> Expression {
+    set(name, wasm!("i32.add", get(name), value))
+}
+
+fn memory_size() -> Expression {
+    wasm!(vec![wasm!("memory.size")])
+}
+
+// runs `body` for as long as `condition` holds
+fn loop_while(condition: Expression, body: Vec<Expression>) -> Expression {
+    let mut l = vec![
+        wasm!("loop"),
+        wasm!("br_if", 1, wasm!("i32.eqz", condition)),
+    ];
+    l.extend(body);
+    l.push(wasm!("br", 0));
+    wasm!("block", wasm!(l))
+}
+
+// The memory for static data from `start` on, and the globals for the shadow stack and the heap
+// after it. The heap is empty until the first allocation.
+pub fn layout(start: usize) -> Vec<Expression> {
+    let stack = (start + 7) & !7;
+    let heap = stack + STACK_SIZE;
+    let pages = (heap >> PAGE_BITS) + 1;
+
+    let constant = |name: &str, value: usize| {
+        wasm!(
+            "global",
+            wasm_dollar!(name),
+            "i32",
+            wasm!("i32.const", value)
+        )
+    };
+    let variable = |name: &str, value: usize| {
+        wasm!(
+            "global",
+            wasm_dollar!(name),
+            wasm!("mut", "i32"),
+            wasm!("i32.const", value)
+        )
+    };
+    vec![
+        wasm!("memory", pages),
+        constant("_stack", stack),
+        variable("_sp", stack),
+        constant("_heap_start", heap),
+        variable("_heap_end", heap),
+        variable("_free_list", 0),
+        variable("_allocated", 0),
+        variable("_threshold", MIN_THRESHOLD as usize),
+    ]
+}
+
+// Returns the address of `size` bytes set to 0, aligned to 8 bytes, with a bit set in `pointers`
+// for each field holding a heap address. Collects garbage first if enough has been allocated
+// since the last time. The first free block big enough is used, or failing that a new one from
+// the end of the heap, growing memory when it runs out.
+fn alloc() -> Expression {
+    wasm!(
+        "func",
+        wasm_dollar!("_alloc"),
+        wasm!("param", wasm_dollar!("size"), "i32"),
+        wasm!("param", wasm_dollar!("pointers"), "i32"),
+        wasm!("result", "i32"),
+        wasm!("local", wasm_dollar!("block"), "i32"),
+        wasm!("local", wasm_dollar!("prev"), "i32"),
+        wasm!("local", wasm_dollar!("free"), "i32"),
+        wasm!("local", wasm_dollar!("pages"), "i32"),
+        wasm!("local", wasm_dollar!("offset"), "i32"),
+        set(
+            "size",
+            wasm!(
+                "i32.and",
+                wasm!("i32.add", get("size"), wasm!("i32.const", HEADER + 7)),
+                wasm!("i32.const", -8)
+            )
+        ),
+        wasm!(
+            "if",
+            wasm!(
+                "i32.gt_u",
+                wasm!("i32.add", global("_allocated"), get("size")),
+                global("_threshold")
+            ),
+            wasm!("then", wasm!("call", wasm_dollar!("_collect")))
+        ),
+        set_global(
+            "_allocated",
+            wasm!("i32.add", global("_allocated"), get("size"))
+        ),
+        set("block", global("_free_list")),
+        wasm!(
+            "block",
+            wasm_dollar!("found"),
+            wasm!(
+                "block",
+                wasm_dollar!("search"),
+                wasm!(
+                    "loop",
+                    wasm_dollar!("next"),
+                    wasm!(
+                        "br_if",
+                        wasm_dollar!("search"),
+                        wasm!("i32.eqz", get("block"))
+                    ),
+                    set("free", wasm!("i32.load", get("block"))),
+                    wasm!(
+                        "if",
+                        wasm!("i32.ge_u", get("free"), get("size")),
+                        wasm!(
+                            "then",
+                            wasm!(
+                                "if",
+                                wasm!(
+                                    "i32.ge_u",
+                                    wasm!("i32.sub", get("free"), get("size")),
+                                    wasm!("i32.const", MIN_BLOCK)
+                                ),
+                                // the free block keeps its place in the list, and its end is used
+                                wasm!(
+                                    "then",
+                                    wasm!(
+                                        "i32.store",
+                                        get("block"),
+                                        wasm!("i32.sub", get("free"), get("size"))
+                                    ),
+                                    add("block", wasm!("i32.sub", get("free"), get("size")))
+                                ),
+                                wasm!(
+                                    "else",
+                                    wasm!(
+                                        "if",
+                                        get("prev"),
+                                        wasm!(
+                                            "then",
+                                            wasm!(
+                                                "i32.store",
+                                                "offset=4",
+                                                get("prev"),
+                                                wasm!("i32.load", "offset=4", get("block"))
+                                            )
+                                        ),
+                                        wasm!(
+                                            "else",
+                                            set_global(
+                                                "_free_list",
+                                                wasm!("i32.load", "offset=4", get("block"))
+                                            )
+                                        )
+                                    ),
+                                    set("size", get("free"))
+                                )
+                            ),
+                            wasm!("br", wasm_dollar!("found"))
+                        )
+                    ),
+                    set("prev", get("block")),
+                    set("block", wasm!("i32.load", "offset=4", get("block"))),
+                    wasm!("br", wasm_dollar!("next"))
+                )
+            ),
+            set("block", global("_heap_end")),
+            set_global("_heap_end", wasm!("i32.add", get("block"), get("size"))),
+            wasm!(
+                "if",
+                wasm!(
+                    "i32.gt_u",
+                    global("_heap_end"),
+                    wasm!("i32.shl", memory_size(), wasm!("i32.const", PAGE_BITS))
+                ),
+                wasm!(
+                    "then",
+                    set(
+                        "pages",
+                        wasm!(
+                            "i32.sub",
+                            wasm!(
+                                "i32.add",
+                                wasm!(
+                                    "i32.shr_u",
+                                    wasm!("i32.sub", global("_heap_end"), wasm!("i32.const", 1)),
+                                    wasm!("i32.const", PAGE_BITS)
+                                ),
+                                wasm!("i32.const", 1)
+                            ),
+                            memory_size()
+                        )
+                    ),
+                    // doubling memory keeps growing it rare, but only the pages needed will do
+                    wasm!(
+                        "if",
+                        wasm!(
+                            "i32.eq",
+                            wasm!(
+                                "memory.grow",
+                                wasm!(
+                                    "select",
+                                    get("pages"),
+                                    memory_size(),
+                                    wasm!("i32.gt_u", get("pages"), memory_size())
+                                )
+                            ),
+                            wasm!("i32.const", -1)
+                        ),
+                        wasm!(
+                            "then",
+                            wasm!(
+                                "if",
+                                wasm!(
+                                    "i32.eq",
+                                    wasm!("memory.grow", get("pages")),
+                                    wasm!("i32.const", -1)
+                                ),
+                                wasm!("then", "unreachable")
+                            )
+                        )
+                    )
+                )
+            )
+        ),
+        wasm!(
+            "i32.store",
+            get("block"),
+            wasm!("i32.or", get("size"), wasm!("i32.const", ALLOCATED))
+        ),
+        wasm!("i32.store", "offset=4", get("block"), get("pointers")),
+        // free blocks hold whatever was there before, which the collector mustn't follow
+        set("offset", wasm!("i32.const", HEADER)),
+        loop_while(
+            wasm!("i32.lt_u", get("offset"), get("size")),
+            vec![
+                wasm!(
+                    "i64.store",
+                    wasm!("i32.add", get("block"), get("offset")),
+                    wasm!("i64.const", 0)
+                ),
+                add("offset", wasm!("i32.const", 8)),
+            ]
+        ),
+        wasm!("i32.add", get("block"), wasm!("i32.const", HEADER))
+    )
+}
+
+// gives back memory returned by $_alloc, putting its block at the start of the free list
+fn free() -> Expression {
+    wasm!(
+        "func",
+        wasm_dollar!("_free"),
+        wasm!("param", wasm_dollar!("address"), "i32"),
+        wasm!("local", wasm_dollar!("block"), "i32"),
+        set(
+            "block",
+            wasm!("i32.sub", get("address"), wasm!("i32.const", HEADER))
+        ),
+        wasm!(
+            "i32.store",
+            get("block"),
+            wasm!(
+                "i32.and",
+                wasm!("i32.load", get("block")),
+                wasm!("i32.const", -8)
+            )
+        ),
+        wasm!("i32.store", "offset=4", get("block"), global("_free_list")),
+        set_global("_free_list", get("block"))
+    )
+}
+
+// Marks the block at the address, and everything it refers to, as in use. The last field of a
+// block is followed in a loop rather than a call, so long lists don't need a deep stack.
+fn mark() -> Expression {
+    let field = |index: Expression| {
+        wasm!(
+            "i32.load",
+            wasm!(
+                "i32.add",
+                get("address"),
+                wasm!("i32.shl", index, wasm!("i32.const", 3))
+            )
+        )
+    };
+
+    wasm!(
+        "func",
+        wasm_dollar!("_mark"),
+        wasm!("param", wasm_dollar!("address"), "i32"),
+        wasm!("local", wasm_dollar!("head"), "i32"),
+        wasm!("local", wasm_dollar!("pointers"), "i32"),
+        wasm!("local", wasm_dollar!("last"), "i32"),
+        wasm!(
+            "loop",
+            wasm_dollar!("object"),
+            wasm!(
+                "if",
+                wasm!("i32.eqz", get("address")),
+                wasm!("then", "return")
+            ),
+            set(
+                "head",
+                wasm!(
+                    "i32.load",
+                    wasm!("i32.sub", get("address"), wasm!("i32.const", HEADER))
+                )
+            ),
+            wasm!(
+                "if",
+                wasm!("i32.and", get("head"), wasm!("i32.const", MARKED)),
+                wasm!("then", "return")
+            ),
+            wasm!(
+                "i32.store",
+                wasm!("i32.sub", get("address"), wasm!("i32.const", HEADER)),
+                wasm!("i32.or", get("head"), wasm!("i32.const", MARKED))
+            ),
+            set(
+                "pointers",
+                wasm!(
+                    "i32.load",
+                    wasm!("i32.sub", get("address"), wasm!("i32.const", HEADER - 4))
+                )
+            ),
+            wasm!(
+                "if",
+                wasm!("i32.eqz", get("pointers")),
+                wasm!("then", "return")
+            ),
+            set(
+                "last",
+                wasm!(
+                    "i32.sub",
+                    wasm!("i32.const", 31),
+                    wasm!("i32.clz", get("pointers"))
+                )
+            ),
+            set(
+                "pointers",
+                wasm!(
+                    "i32.xor",
+                    get("pointers"),
+                    wasm!("i32.shl", wasm!("i32.const", 1), get("last"))
+                )
+            ),
+            loop_while(
+                get("pointers"),
+                vec![
+                    wasm!(
+                        "call",
+                        wasm_dollar!("_mark"),
+                        field(wasm!("i32.ctz", get("pointers")))
+                    ),
+                    set(
+                        "pointers",
+                        wasm!(
+                            "i32.and",
+                            get("pointers"),
+                            wasm!("i32.sub", get("pointers"), wasm!("i32.const", 1))
+                        )
+                    ),
+                ]
+            ),
+            set("address", field(get("last"))),
+            wasm!("br", wasm_dollar!("object"))
+        )
+    )
+}
+
+// Frees every block that can't be reached from the shadow stack. The free list is built again
+// from scratch, with neighbouring free blocks joined together, and free blocks at the end of the
+// heap are given back to it.
+fn collect() -> Expression {
+    wasm!(
+        "func",
+        wasm_dollar!("_collect"),
+        wasm!("local", wasm_dollar!("block"), "i32"),
+        wasm!("local", wasm_dollar!("head"), "i32"),
+        wasm!("local", wasm_dollar!("size"), "i32"),
+        wasm!("local", wasm_dollar!("run"), "i32"),
+        wasm!("local", wasm_dollar!("live"), "i32"),
+        set("block", global("_stack")),
+        loop_while(
+            wasm!("i32.lt_u", get("block"), global("_sp")),
+            vec![
+                wasm!(
+                    "call",
+                    wasm_dollar!("_mark"),
+                    wasm!("i32.load", get("block"))
+                ),
+                add("block", wasm!("i32.const", 4)),
+            ]
+        ),
+        set_global("_free_list", wasm!("i32.const", 0)),
+        set("block", global("_heap_start")),
+        loop_while(
+            wasm!("i32.lt_u", get("block"), global("_heap_end")),
+            vec![
+                set("head", wasm!("i32.load", get("block"))),
+                set(
+                    "size",
+                    wasm!("i32.and", get("head"), wasm!("i32.const", -8))
+                ),
+                wasm!(
+                    "if",
+                    wasm!("i32.and", get("head"), wasm!("i32.const", MARKED)),
+                    wasm!(
+                        "then",
+                        wasm!(
+                            "i32.store",
+                            get("block"),
+                            wasm!("i32.xor", get("head"), wasm!("i32.const", MARKED))
+                        ),
+                        add("live", get("size")),
+                        set("run", wasm!("i32.const", 0))
+                    ),
+                    wasm!(
+                        "else",
+                        wasm!(
+                            "if",
+                            get("run"),
+                            wasm!(
+                                "then",
+                                wasm!(
+                                    "i32.store",
+                                    get("run"),
+                                    wasm!("i32.add", wasm!("i32.load", get("run")), get("size"))
+                                )
+                            ),
+                            wasm!(
+                                "else",
+                                wasm!("i32.store", get("block"), get("size")),
+                                wasm!("i32.store", "offset=4", get("block"), global("_free_list")),
+                                set_global("_free_list", get("block")),
+                                set("run", get("block"))
+                            )
+                        )
+                    )
+                ),
+                add("block", get("size")),
+            ]
+        ),
+        // the last run is at the start of the free list
+        wasm!(
+            "if",
+            get("run"),
+            wasm!(
+                "then",
+                set_global("_free_list", wasm!("i32.load", "offset=4", get("run"))),
+                set_global("_heap_end", get("run"))
+            )
+        ),
+        set_global("_allocated", wasm!("i32.const", 0)),
+        set_global(
+            "_threshold",
+            wasm!(
+                "select",
+                wasm!("i32.shl", get("live"), wasm!("i32.const", 1)),
+                wasm!("i32.const", MIN_THRESHOLD),
+                wasm!(
+                    "i32.gt_u",
+                    get("live"),
+                    wasm!("i32.const", MIN_THRESHOLD / 2)
+                )
+            )
+        )
+    )
+}
+
+// Sets aside a frame of slots on the shadow stack for the caller, cleared so the collector
+// doesn't follow addresses left over from an earlier frame, and returns the address of the first
+// slot. Running out of shadow stack traps.
+fn enter() -> Expression {
+    wasm!(
+        "func",
+        wasm_dollar!("_enter"),
+        wasm!("param", wasm_dollar!("slots"), "i32"),
+        wasm!("result", "i32"),
+        wasm!("local", wasm_dollar!("frame"), "i32"),
+        wasm!("local", wasm_dollar!("slot"), "i32"),
+        set("frame", global("_sp")),
+        set("slot", get("frame")),
+        set_global(
+            "_sp",
+            wasm!(
+                "i32.add",
+                get("frame"),
+                wasm!("i32.shl", get("slots"), wasm!("i32.const", 2))
+            )
+        ),
+        wasm!(
+            "if",
+            wasm!("i32.gt_u", global("_sp"), global("_heap_start")),
+            wasm!("then", "unreachable")
+        ),
+        loop_while(
+            wasm!("i32.lt_u", get("slot"), global("_sp")),
+            vec![
+                wasm!("i32.store", get("slot"), wasm!("i32.const", 0)),
+                add("slot", wasm!("i32.const", 4)),
+            ]
+        ),
+        get("frame")
+    )
+}
+
+// keeps a heap address in a slot on the shadow stack, returning it
+fn root() -> Expression {
+    wasm!(
+        "func",
+        wasm_dollar!("_root"),
+        wasm!("param", wasm_dollar!("slot"), "i32"),
+        wasm!("param", wasm_dollar!("address"), "i32"),
+        wasm!("result", "i32"),
+        wasm!("i32.store", get("slot"), get("address")),
+        get("address")
+    )
+}
+
+pub fn helpers() -> Vec<Expression> {
+    vec![alloc(), free(), mark(), collect(), enter(), root()]
+}
+
+// the address of a new frame with `slots` slots on the shadow stack
+pub fn push_frame(slots: usize) -> Expression {
+    wasm!("call", wasm_dollar!("_enter"), wasm!("i32.const", slots))
+}
+
+// keeps `address` in a slot of the frame, leaving it on the wasm stack too
+pub fn keep(frame: Expression, slot: usize, address: Expression) -> Expression {
+    wasm!(
+        "call",
+        wasm_dollar!("_root"),
+        wasm!("i32.add", frame, wasm!("i32.const", slot * 4)),
+        address
+    )
+}
+
+// frees the frame and every slot after it
+pub fn pop_frame(frame: Expression) -> Expression {
+    set_global("_sp", frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmtime::{Engine, Instance, Module, Store};
+
+    const HEAP_START: i32 = 64 + STACK_SIZE as i32;
+
+    // Runs the body of a function returning an int in a module with static data up to address 60,
+    // along with the number of pages of memory it finished with. $push and $pop put an address on
+    // the shadow stack and take it off again.
+    fn run(body: &str) -> (i32, u64) {
+        let mut module = wasm!("module", wasm!("export", "\"memory\"", wasm!("memory", 0)));
+        for expr in layout(60).into_iter().chain(helpers()) {
+            module = module.extend(expr);
+        }
+        let source = module.to_string();
+        let source = format!(
+            "{}
+            (func $push (param $address i32)
+                (i32.store (global.get $_sp) (local.get $address))
+                (global.set $_sp (i32.add (global.get $_sp) (i32.const 4))))
+            (func $pop (result i32)
+                (global.set $_sp (i32.sub (global.get $_sp) (i32.const 4)))
+                (i32.load (global.get $_sp)))
+            (func (export \"test\") (result i32) {}))",
+            &source[..source.len() - 1],
+            body
+        );
+
+        let engine = Engine::default();
+        let mut store = Store::new(&engine, ());
+        let module = Module::new(&engine, wabt::wat2wasm(source).unwrap()).unwrap();
+        let instance = Instance::new(&mut store, &module, &[]).unwrap();
+        let test = instance
+            .get_typed_func::<(), i32, _>(&mut store, "test")
+            .unwrap();
+        let result = test.call(&mut store, ()).unwrap();
+        let memory = instance.get_memory(&mut store, "memory").unwrap();
+        (result, memory.size(&store))
+    }
+
+    // Builds a list of the ints from 1 to $n, as blocks of an int and the address of the next
+    // block, with the first block on the shadow stack. Needs the locals $n, $i and $cell.
+    const LIST: &str = "(call $push (i32.const 0))
+        (local.set $i (local.get $n))
+        (block (loop
+            (br_if 1 (i32.eqz (local.get $i)))
+            (local.set $cell (call $_alloc (i32.const 16) (i32.const 2)))
+            (i64.store (local.get $cell) (i64.extend_i32_u (local.get $i)))
+            (i32.store offset=8 (local.get $cell) (call $pop))
+            (call $push (local.get $cell))
+            (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+            (br 0)))";
+
+    // adds up the list on top of the shadow stack into $i
+    const SUM: &str = "(local.set $cell (call $pop))
+        (call $push (local.get $cell))
+        (local.set $i (i32.const 0))
+        (block (loop
+            (br_if 1 (i32.eqz (local.get $cell)))
+            (local.set $i (i32.add (local.get $i) (i32.load (local.get $cell))))
+            (local.set $cell (i32.load offset=8 (local.get $cell)))
+            (br 0)))";
+
+    #[test]
+    fn test_alloc() {
+        // each block is its size rounded up to a word, plus the header
+        assert_eq!(
+            run("(call $_alloc (i32.const 4) (i32.const 0))").0,
+            HEAP_START + 8
+        );
+        let (distance, _) = run("(local $a i32)
+            (local.set $a (call $_alloc (i32.const 4) (i32.const 0)))
+            (i32.sub (call $_alloc (i32.const 20) (i32.const 0)) (local.get $a))");
+        assert_eq!(distance, 16);
+
+        // memory that is given out is cleared, even when it was used before
+        let (cleared, _) = run("(local $a i32)
+            (local.set $a (call $_alloc (i32.const 16) (i32.const 0)))
+            (i64.store offset=8 (local.get $a) (i64.const -1))
+            (call $_free (local.get $a))
+            (i32.load offset=8 (call $_alloc (i32.const 16) (i32.const 0)))");
+        assert_eq!(cleared, 0);
+    }
+
+    #[test]
+    fn test_free() {
+        // a block that fits exactly is reused
+        let (reused, _) = run("(local $a i32)
+            (local.set $a (call $_alloc (i32.const 100) (i32.const 0)))
+            (drop (call $_alloc (i32.const 8) (i32.const 0)))
+            (call $_free (local.get $a))
+            (i32.eq (call $_alloc (i32.const 100) (i32.const 0)) (local.get $a))");
+        assert_eq!(reused, 1);
+
+        // a bigger one is split, handing out its end first
+        let (split, _) = run("(local $a i32)
+            (local.set $a (call $_alloc (i32.const 1000) (i32.const 0)))
+            (drop (call $_alloc (i32.const 8) (i32.const 0)))
+            (call $_free (local.get $a))
+            (drop (call $_alloc (i32.const 8) (i32.const 0)))
+            (i32.sub (call $_alloc (i32.const 8) (i32.const 0)) (local.get $a))");
+        assert_eq!(split, 1008 - 2 * 16);
+    }
+
+    #[test]
+    fn test_grow() {
+        // blocks past the first page can be written to, including ones bigger than a page
+        let (last, pages) = run("(local $i i32)
+            (local $a i32)
+            (loop $next
+                (local.set $a (call $_alloc (i32.const 10000) (i32.const 0)))
+                (call $push (local.get $a))
+                (i32.store8 offset=9999 (local.get $a) (i32.const 1))
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br_if $next (i32.lt_u (local.get $i) (i32.const 300))))
+            (local.set $a (call $_alloc (i32.const 200000) (i32.const 0)))
+            (i32.store8 offset=199999 (local.get $a) (i32.const 1))
+            (i32.load8_u offset=199999 (local.get $a))");
+        assert_eq!(last, 1);
+        assert!(pages >= 45, "{} pages", pages);
+
+        // freed memory is used again rather than growing memory
+        let (_, pages) = run("(local $i i32)
+            (loop $next
+                (call $push (call $_alloc (i32.const 1000000) (i32.const 0)))
+                (call $_free (call $pop))
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br_if $next (i32.lt_u (local.get $i) (i32.const 1000))))
+            (i32.const 0)");
+        assert!(pages <= 48, "{} pages", pages);
+    }
+
+    #[test]
+    fn test_collect() {
+        // blocks that can be reached from the shadow stack are kept however much garbage there
+        // is, and the rest are reused, here for 4000 times as much memory as the list takes
+        let (sum, pages) = run(&format!(
+            "(local $n i32)
+            (local $i i32)
+            (local $cell i32)
+            (local $garbage i32)
+            (local.set $n (i32.const 1000))
+            {}
+            (local.set $garbage (i32.const 4000000))
+            (block (loop
+                (br_if 1 (i32.eqz (local.get $garbage)))
+                (drop (call $_alloc (i32.const 16) (i32.const 2)))
+                (local.set $garbage (i32.sub (local.get $garbage) (i32.const 1)))
+                (br 0)))
+            {}
+            (local.get $i)",
+            LIST, SUM
+        ));
+        assert_eq!(sum, 1000 * 1001 / 2);
+        assert!(pages <= 64, "{} pages", pages);
+
+        // a long list is marked without running out of stack, and once it is off the shadow
+        // stack the heap is empty again
+        let (sum, _) = run(&format!(
+            "(local $n i32)
+            (local $i i32)
+            (local $cell i32)
+            (local.set $n (i32.const 60000))
+            {}
+            {}
+            (drop (call $pop))
+            (call $_collect)
+            (i32.mul
+                (local.get $i)
+                (i32.eq (global.get $_heap_end) (global.get $_heap_start)))",
+            LIST, SUM
+        ));
+        assert_eq!(sum, 30000 * 60001);
+    }
+}
@@ -0,0 +1,101 @@
+use super::heap::{keep, pop_frame, push_frame};
+use crate::ast::VariableType;
+use crate::wasm::Expression;
+use crate::{wasm, wasm_dollar};
+
+// A list is the address of its first cell on the heap, or 0 when it is empty. Each cell holds an
+// element in its first field and the rest of the list in its second.
+const CELL_SIZE: i32 = 16;
+const TAIL: i32 = 8;
+// the bits for the fields of a cell holding heap addresses
+const TAIL_POINTER: i32 = 2;
+const HEAD_POINTER: i32 = 1;
+
+fn get(name: &str) -> Expression {
+    wasm!("local.get", wasm_dollar!(name))
+}
+
+// the wasm type an element is stored as, and the name of the helper making cells for it
+fn cell_type(element: &VariableType) -> (Expression, String) {
+    match element.is_heap() {
+        true => (wasm!("i32"), "_cons_heap".to_string()),
+        false => (element.to_wasm(), format!("_cons_{}", element.to_wasm())),
+    }
+}
+
+// Makes a cell holding `$head` in front of `$tail`. Both are kept on the shadow stack while it is
+// allocated, in case the collector runs.
+fn helper(element: &VariableType) -> Expression {
+    let (wasm_type, name) = cell_type(element);
+    let pointers = match element.is_heap() {
+        true => TAIL_POINTER | HEAD_POINTER,
+        false => TAIL_POINTER,
+    };
+    let mut func = vec![
+        wasm!("func"),
+        wasm_dollar!(&name),
+        wasm!("param", wasm_dollar!("head"), wasm_type),
+        wasm!("param", wasm_dollar!("tail"), "i32"),
+        wasm!("result", "i32"),
+        wasm!("local", wasm_dollar!("frame"), "i32"),
+        wasm!("local", wasm_dollar!("cell"), "i32"),
+        wasm!("local.set", wasm_dollar!("frame"), push_frame(2)),
+        wasm!("drop", keep(get("frame"), 0, get("tail"))),
+    ];
+    if element.is_heap() {
+        func.push(wasm!("drop", keep(get("frame"), 1, get("head"))));
+    }
+    func.extend(vec![
+        wasm!(
+            "local.set",
+            wasm_dollar!("cell"),
+            wasm!(
+                "call",
+                wasm_dollar!("_alloc"),
+                wasm!("i32.const", CELL_SIZE),
+                wasm!("i32.const", pointers)
+            )
+        ),
+        wasm!(
+            format!("{}.store", cell_type(element).0),
+            get("cell"),
+            get("head")
+        ),
+        wasm!(
+            "i32.store",
+            format!("offset={}", TAIL),
+            get("cell"),
+            get("tail")
+        ),
+        pop_frame(get("frame")),
+        get("cell"),
+    ]);
+    wasm!(func)
+}
+
+pub fn helpers() -> Vec<Expression> {
+    use VariableType::*;
+
+    vec![
+        helper(&Int),
+        helper(&Int64),
+        helper(&Float),
+        helper(&Double),
+        helper(&List(Box::new(Unknown))),
+    ]
+}
+
+// a list with `head` in front of `tail`
+pub fn cons(element: &VariableType, head: Expression, tail: Expression) -> Expression {
+    wasm!("call", wasm_dollar!(&cell_type(element).1), head, tail)
+}
+
+// the first element of a list that isn't empty
+pub fn head(element: &VariableType, list: Expression) -> Expression {
+    wasm!(format!("{}.load", cell_type(element).0), list)
+}
+
+// everything after the first element of a list that isn't empty
+pub fn tail(list: Expression) -> Expression {
+    wasm!("i32.load", format!("offset={}", TAIL), list)
+}
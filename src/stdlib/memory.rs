@@ -0,0 +1,47 @@
+use crate::wasm::Expression;
+use crate::{wasm, wasm_dollar};
+
+// Linear memory starts with a scratch area that only the library writes to, where it formats
+// text and talks to WASI. Static data, such as string literals, follows it.
+
+// the iovec given to fd_write, an address and a length
+const IOVEC: i32 = 0;
+// where fd_write puts the number of bytes it wrote
+const WRITTEN: i32 = 8;
+// where text is formatted before it is written
+pub const BUFFER: i32 = 16;
+pub const BUFFER_SIZE: i32 = 32;
+pub const DATA_START: usize = (BUFFER + BUFFER_SIZE) as usize;
+
+// writes `len` bytes from `address` to stdout
+pub fn helper() -> Expression {
+    wasm!(
+        "func",
+        wasm_dollar!("_write"),
+        wasm!("param", wasm_dollar!("address"), "i32"),
+        wasm!("param", wasm_dollar!("len"), "i32"),
+        wasm!(
+            "i32.store",
+            wasm!("i32.const", IOVEC),
+            wasm!("local.get", wasm_dollar!("address"))
+        ),
+        wasm!(
+            "i32.store",
+            wasm!("i32.const", IOVEC + 4),
+            wasm!("local.get", wasm_dollar!("len"))
+        ),
+        wasm!(
+            "call",
+            wasm_dollar!("fd_write"),
+            wasm!("i32.const", 1),
+            wasm!("i32.const", IOVEC),
+            wasm!("i32.const", 1),
+            wasm!("i32.const", WRITTEN)
+        ),
+        wasm!("drop")
+    )
+}
+
+pub fn write(address: Expression, len: Expression) -> Expression {
+    wasm!("call", wasm_dollar!("_write"), address, len)
+}
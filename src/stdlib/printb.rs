@@ -0,0 +1,58 @@
+use super::memory::{write, BUFFER};
+use super::Func;
+use crate::ast::{FunctionSignature, VariableType};
+use crate::wasm::Expression;
+use crate::{wasm, wasm_dollar};
+
+// the bytes of a word of up to 8 letters, as stored in memory by i64.store
+fn word(s: &str) -> i64 {
+    s.bytes().rev().fold(0, |word, b| word << 8 | i64::from(b))
+}
+
+// writes true or false to stdout
+pub fn helper() -> Expression {
+    let put = |branch: &str, s: &str| {
+        wasm!(
+            branch,
+            wasm!(
+                "i64.store",
+                wasm!("i32.const", BUFFER),
+                wasm!("i64.const", word(s))
+            ),
+            write(wasm!("i32.const", BUFFER), wasm!("i32.const", s.len()))
+        )
+    };
+
+    wasm!(
+        "func",
+        wasm_dollar!("_putb"),
+        wasm!("param", wasm_dollar!("bool"), "i32"),
+        wasm!(
+            "if",
+            wasm!("local.get", wasm_dollar!("bool")),
+            put("then", "true"),
+            put("else", "false")
+        )
+    )
+}
+
+pub fn new() -> Func {
+    Func {
+        sig: FunctionSignature {
+            arg_types: vec![VariableType::Bool],
+            return_type: VariableType::Unit,
+        },
+        wasm: wasm!(
+            "func",
+            wasm_dollar!("printb"),
+            wasm!("param", wasm_dollar!("bool"), "i32"),
+            wasm!("result", "i32"),
+            wasm!(
+                "call",
+                wasm_dollar!("_putb"),
+                wasm!("local.get", wasm_dollar!("bool"))
+            ),
+            wasm!("i32.const", 0)
+        ),
+    }
+}
@@ -1,33 +1,74 @@
+use super::memory::{write, BUFFER};
 use super::Func;
 use crate::ast::{FunctionSignature, VariableType};
+use crate::wasm::Expression;
 use crate::{wasm, wasm_dollar};
 
+fn char() -> Expression {
+    wasm!("local.get", wasm_dollar!("char"))
+}
+
+// stores the char in the buffer as the `len` bytes of its UTF-8 encoding, and writes them
+fn encode(len: i32) -> Vec<Expression> {
+    let lead = [0, 0, 0xc0, 0xe0, 0xf0][len as usize];
+    let mut instructions = vec![];
+    for i in 0..len {
+        let shifted = wasm!("i32.shr_u", char(), wasm!("i32.const", 6 * (len - 1 - i)));
+        let byte = match i {
+            0 => wasm!("i32.or", wasm!("i32.const", lead), shifted),
+            _ => wasm!(
+                "i32.or",
+                wasm!("i32.const", 0x80),
+                wasm!("i32.and", shifted, wasm!("i32.const", 0x3f))
+            ),
+        };
+        instructions.push(wasm!("i32.store8", wasm!("i32.const", BUFFER + i), byte));
+    }
+    instructions.push(write(wasm!("i32.const", BUFFER), wasm!("i32.const", len)));
+    instructions
+}
+
+// writes the character to stdout
+pub fn helper() -> Expression {
+    // branches on the largest code point encoded in each number of bytes
+    let mut instructions = encode(4);
+    for (len, max) in [(3, 0xffff), (2, 0x7ff), (1, 0x7f)].iter() {
+        let mut then = vec![wasm!("then")];
+        then.extend(encode(*len));
+        let mut otherwise = vec![wasm!("else")];
+        otherwise.extend(instructions);
+        instructions = vec![wasm!(
+            "if",
+            wasm!("i32.le_u", char(), wasm!("i32.const", *max)),
+            wasm!(then),
+            wasm!(otherwise)
+        )];
+    }
+
+    let mut func = wasm!(
+        "func",
+        wasm_dollar!("_putc"),
+        wasm!("param", wasm_dollar!("char"), "i32")
+    );
+    for instruction in instructions {
+        func = func.extend(instruction);
+    }
+    func
+}
+
 pub fn new() -> Func {
     Func {
         sig: FunctionSignature {
-            arg_types: vec![],
-            return_type: VariableType::Int,
+            arg_types: vec![VariableType::Int],
+            return_type: VariableType::Unit,
         },
         wasm: wasm!(
             "func",
             wasm_dollar!("printc"),
             wasm!("param", wasm_dollar!("char"), "i32"),
-            wasm!("i32.store", wasm!("i32.const", 0), wasm!("i32.const", 8)),
-            wasm!("i32.store", wasm!("i32.const", 4), wasm!("i32.const", 2)),
-            wasm!(
-                "i32.store",
-                wasm!("i32.const", 8),
-                wasm!("local.get", wasm_dollar!("char"))
-            ),
-            wasm!(
-                "call",
-                wasm_dollar!("fd_write"),
-                wasm!("i32.const", 1),
-                wasm!("i32.const", 0),
-                wasm!("i32.const", 1),
-                wasm!("i32.const", 20)
-            ),
-            wasm!("drop")
+            wasm!("result", "i32"),
+            wasm!("call", wasm_dollar!("_putc"), char()),
+            wasm!("i32.const", 0)
         ),
     }
 }
@@ -0,0 +1,230 @@
+use super::Func;
+use crate::ast::{FunctionSignature, VariableType};
+use crate::wasm::Expression;
+use crate::{wasm, wasm_dollar};
+
+fn get(name: &str) -> Expression {
+    wasm!("local.get", wasm_dollar!(name))
+}
+
+fn set(name: &str, value: Expression) -> Expression {
+    wasm!("local.set", wasm_dollar!(name), value)
+}
+
+fn putc(c: char) -> Expression {
+    wasm!("call", wasm_dollar!("_putc"), wasm!("i32.const", c as i32))
+}
+
+// loops until `done` holds, checking before each run of `body`
+fn until(done: Expression, body: Vec<Expression>) -> Expression {
+    let mut l = vec![wasm!("loop"), wasm!("br_if", 1, done)];
+    l.extend(body);
+    l.push(wasm!("br", 0));
+    wasm!("block", wasm!(l))
+}
+
+// Writes the number to stdout in decimal, with as many digits after the point as there are
+// zeros in `scale` but leaving off trailing zeros. NaN and infinity are written as nan and inf,
+// and -0 keeps its sign.
+pub fn helper() -> Expression {
+    wasm!(
+        "func",
+        wasm_dollar!("_putd"),
+        wasm!("param", wasm_dollar!("num"), "f64"),
+        wasm!("param", wasm_dollar!("scale"), "f64"),
+        wasm!("local", wasm_dollar!("whole"), "f64"),
+        wasm!("local", wasm_dollar!("power"), "f64"),
+        wasm!("local", wasm_dollar!("frac"), "i64"),
+        wasm!("local", wasm_dollar!("divisor"), "i64"),
+        wasm!(
+            "if",
+            wasm!("f64.ne", get("num"), get("num")),
+            wasm!("then", putc('n'), putc('a'), putc('n'), wasm!("return"))
+        ),
+        // testing the sign bit rather than comparing with 0 keeps the sign of -0
+        wasm!(
+            "if",
+            wasm!(
+                "i64.lt_s",
+                wasm!("i64.reinterpret_f64", get("num")),
+                wasm!("i64.const", 0)
+            ),
+            wasm!("then", putc('-'), set("num", wasm!("f64.neg", get("num"))))
+        ),
+        wasm!(
+            "if",
+            wasm!("f64.eq", get("num"), wasm!("f64.const", "inf")),
+            wasm!("then", putc('i'), putc('n'), putc('f'), wasm!("return"))
+        ),
+        set("whole", wasm!("f64.floor", get("num"))),
+        set(
+            "frac",
+            wasm!(
+                "i64.trunc_f64_s",
+                wasm!(
+                    "f64.nearest",
+                    wasm!(
+                        "f64.mul",
+                        wasm!("f64.sub", get("num"), get("whole")),
+                        get("scale")
+                    )
+                )
+            )
+        ),
+        // rounding the fraction up can carry into the whole part
+        wasm!(
+            "if",
+            wasm!(
+                "f64.ge",
+                wasm!("f64.convert_i64_s", get("frac")),
+                get("scale")
+            ),
+            wasm!(
+                "then",
+                set(
+                    "whole",
+                    wasm!("f64.add", get("whole"), wasm!("f64.const", 1))
+                ),
+                set("frac", wasm!("i64.const", 0))
+            )
+        ),
+        // the largest power of ten in the whole part
+        set("power", wasm!("f64.const", 1)),
+        until(
+            wasm!(
+                "f64.gt",
+                wasm!("f64.mul", get("power"), wasm!("f64.const", 10)),
+                get("whole")
+            ),
+            vec![set(
+                "power",
+                wasm!("f64.mul", get("power"), wasm!("f64.const", 10))
+            )]
+        ),
+        until(
+            wasm!("f64.lt", get("power"), wasm!("f64.const", 1)),
+            vec![
+                wasm!(
+                    "call",
+                    wasm_dollar!("_putc"),
+                    wasm!(
+                        "i32.add",
+                        wasm!("i32.const", 48),
+                        wasm!(
+                            "i32.trunc_f64_s",
+                            wasm!("f64.floor", wasm!("f64.div", get("whole"), get("power")))
+                        )
+                    )
+                ),
+                set(
+                    "whole",
+                    wasm!(
+                        "f64.sub",
+                        get("whole"),
+                        wasm!(
+                            "f64.mul",
+                            wasm!("f64.floor", wasm!("f64.div", get("whole"), get("power"))),
+                            get("power")
+                        )
+                    )
+                ),
+                set(
+                    "power",
+                    wasm!("f64.div", get("power"), wasm!("f64.const", 10))
+                ),
+            ]
+        ),
+        putc('.'),
+        set(
+            "divisor",
+            wasm!(
+                "i64.trunc_f64_s",
+                wasm!("f64.div", get("scale"), wasm!("f64.const", 10))
+            )
+        ),
+        // leave off trailing zeros, but keep at least one digit
+        until(
+            wasm!(
+                "i32.or",
+                wasm!(
+                    "i64.ne",
+                    wasm!("i64.rem_u", get("frac"), wasm!("i64.const", 10)),
+                    wasm!("i64.const", 0)
+                ),
+                wasm!("i64.le_u", get("divisor"), wasm!("i64.const", 1))
+            ),
+            vec![
+                set(
+                    "frac",
+                    wasm!("i64.div_u", get("frac"), wasm!("i64.const", 10))
+                ),
+                set(
+                    "divisor",
+                    wasm!("i64.div_u", get("divisor"), wasm!("i64.const", 10))
+                ),
+            ]
+        ),
+        until(
+            wasm!("i64.eqz", get("divisor")),
+            vec![
+                wasm!(
+                    "call",
+                    wasm_dollar!("_putc"),
+                    wasm!(
+                        "i32.add",
+                        wasm!("i32.const", 48),
+                        wasm!(
+                            "i32.wrap_i64",
+                            wasm!("i64.div_u", get("frac"), get("divisor"))
+                        )
+                    )
+                ),
+                set("frac", wasm!("i64.rem_u", get("frac"), get("divisor"))),
+                set(
+                    "divisor",
+                    wasm!("i64.div_u", get("divisor"), wasm!("i64.const", 10))
+                ),
+            ]
+        )
+    )
+}
+
+// the digits after the point that a float and a double are written with
+pub const FLOAT_SCALE: f64 = 1e6;
+pub const DOUBLE_SCALE: f64 = 1e15;
+
+fn print(name: &str, var_type: VariableType, scale: f64) -> Func {
+    let num = match var_type {
+        VariableType::Float => wasm!("f64.promote_f32", get("num")),
+        _ => get("num"),
+    };
+    Func {
+        sig: FunctionSignature {
+            arg_types: vec![var_type.clone()],
+            return_type: VariableType::Unit,
+        },
+        wasm: wasm!(
+            "func",
+            wasm_dollar!(name),
+            wasm!("param", wasm_dollar!("num"), var_type.to_wasm()),
+            wasm!("result", "i32"),
+            wasm!(
+                "call",
+                wasm_dollar!("_putd"),
+                num,
+                wasm!("f64.const", scale)
+            ),
+            wasm!("i32.const", 0)
+        ),
+    }
+}
+
+pub fn new() -> Vec<(&'static str, Func)> {
+    vec![
+        ("printf", print("printf", VariableType::Float, FLOAT_SCALE)),
+        (
+            "printd",
+            print("printd", VariableType::Double, DOUBLE_SCALE),
+        ),
+    ]
+}
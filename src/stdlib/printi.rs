@@ -1,50 +1,39 @@
 use super::Func;
 use crate::ast::{FunctionSignature, VariableType};
+use crate::wasm::Expression;
 use crate::{wasm, wasm_dollar};
 
+// writes the int to stdout, formatted as the int64 it widens to
+pub fn helper() -> Expression {
+    wasm!(
+        "func",
+        wasm_dollar!("_puti"),
+        wasm!("param", wasm_dollar!("num"), "i32"),
+        wasm!(
+            "call",
+            wasm_dollar!("_putl"),
+            wasm!("i64.extend_i32_s", wasm!("local.get", wasm_dollar!("num")))
+        )
+    )
+}
+
 pub fn new() -> Func {
     Func {
         sig: FunctionSignature {
-            arg_types: vec![],
-            return_type: VariableType::Int,
+            arg_types: vec![VariableType::Int],
+            return_type: VariableType::Unit,
         },
         wasm: wasm!(
             "func",
             wasm_dollar!("printi"),
             wasm!("param", wasm_dollar!("num"), "i32"),
+            wasm!("result", "i32"),
             wasm!(
-                "if",
-                wasm!(
-                    "i32.ne",
-                    wasm!("i32.const", 0),
-                    wasm!("local.get", wasm_dollar!("num"))
-                ),
-                wasm!(
-                    "then",
-                    wasm!(
-                        "call",
-                        wasm_dollar!("printi"),
-                        wasm!(
-                            "i32.div_u",
-                            wasm!("local.get", wasm_dollar!("num")),
-                            wasm!("i32.const", 10)
-                        )
-                    ),
-                    wasm!(
-                        "call",
-                        wasm_dollar!("printc"),
-                        wasm!(
-                            "i32.add",
-                            wasm!("i32.const", 48),
-                            wasm!(
-                                "i32.rem_u",
-                                wasm!("local.get", wasm_dollar!("num")),
-                                wasm!("i32.const", 10)
-                            )
-                        )
-                    )
-                )
-            )
+                "call",
+                wasm_dollar!("_puti"),
+                wasm!("local.get", wasm_dollar!("num"))
+            ),
+            wasm!("i32.const", 0)
         ),
     }
 }
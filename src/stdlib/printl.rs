@@ -0,0 +1,100 @@
+use super::memory::{write, BUFFER, BUFFER_SIZE};
+use super::Func;
+use crate::ast::{FunctionSignature, VariableType};
+use crate::wasm::Expression;
+use crate::{wasm, wasm_dollar};
+
+// the digits are written backwards from the end of the buffer
+const END: i32 = BUFFER + BUFFER_SIZE;
+
+fn get(name: &str) -> Expression {
+    wasm!("local.get", wasm_dollar!(name))
+}
+
+fn set(name: &str, value: Expression) -> Expression {
+    wasm!("local.set", wasm_dollar!(name), value)
+}
+
+// the position of the next character to the left
+fn back() -> Expression {
+    set("pos", wasm!("i32.sub", get("pos"), wasm!("i32.const", 1)))
+}
+
+// Writes the int64 to stdout all at once, with a '-' when it is negative. The magnitude is
+// read as unsigned, so negating the smallest int64 still gives the right digits.
+pub fn helper() -> Expression {
+    wasm!(
+        "func",
+        wasm_dollar!("_putl"),
+        wasm!("param", wasm_dollar!("num"), "i64"),
+        wasm!("local", wasm_dollar!("rest"), "i64"),
+        wasm!("local", wasm_dollar!("pos"), "i32"),
+        set("pos", wasm!("i32.const", END)),
+        set(
+            "rest",
+            wasm!(
+                "if",
+                wasm!("result", "i64"),
+                wasm!("i64.lt_s", get("num"), wasm!("i64.const", 0)),
+                wasm!("then", wasm!("i64.sub", wasm!("i64.const", 0), get("num"))),
+                wasm!("else", get("num"))
+            )
+        ),
+        // there is always at least one digit, so 0 is written as 0
+        wasm!(
+            "loop",
+            back(),
+            wasm!(
+                "i32.store8",
+                get("pos"),
+                wasm!(
+                    "i32.add",
+                    wasm!("i32.const", 48),
+                    wasm!(
+                        "i32.wrap_i64",
+                        wasm!("i64.rem_u", get("rest"), wasm!("i64.const", 10))
+                    )
+                )
+            ),
+            set(
+                "rest",
+                wasm!("i64.div_u", get("rest"), wasm!("i64.const", 10))
+            ),
+            wasm!(
+                "br_if",
+                0,
+                wasm!("i64.ne", get("rest"), wasm!("i64.const", 0))
+            )
+        ),
+        wasm!(
+            "if",
+            wasm!("i64.lt_s", get("num"), wasm!("i64.const", 0)),
+            wasm!(
+                "then",
+                back(),
+                wasm!("i32.store8", get("pos"), wasm!("i32.const", 45))
+            )
+        ),
+        write(
+            get("pos"),
+            wasm!("i32.sub", wasm!("i32.const", END), get("pos"))
+        )
+    )
+}
+
+pub fn new() -> Func {
+    Func {
+        sig: FunctionSignature {
+            arg_types: vec![VariableType::Int64],
+            return_type: VariableType::Unit,
+        },
+        wasm: wasm!(
+            "func",
+            wasm_dollar!("printl"),
+            wasm!("param", wasm_dollar!("num"), "i64"),
+            wasm!("result", "i32"),
+            wasm!("call", wasm_dollar!("_putl"), get("num")),
+            wasm!("i32.const", 0)
+        ),
+    }
+}
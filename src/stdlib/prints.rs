@@ -0,0 +1,32 @@
+use super::memory::write;
+use super::Func;
+use crate::ast::{FunctionSignature, VariableType};
+use crate::{wasm, wasm_dollar};
+
+// writes the string to stdout
+pub fn new() -> Func {
+    Func {
+        sig: FunctionSignature {
+            arg_types: vec![VariableType::String],
+            return_type: VariableType::Unit,
+        },
+        wasm: wasm!(
+            "func",
+            wasm_dollar!("prints"),
+            wasm!("param", wasm_dollar!("string"), "i64"),
+            wasm!("result", "i32"),
+            write(
+                wasm!("i32.wrap_i64", wasm!("local.get", wasm_dollar!("string"))),
+                wasm!(
+                    "i32.wrap_i64",
+                    wasm!(
+                        "i64.shr_u",
+                        wasm!("local.get", wasm_dollar!("string")),
+                        wasm!("i64.const", 32)
+                    )
+                )
+            ),
+            wasm!("i32.const", 0)
+        ),
+    }
+}
@@ -0,0 +1,47 @@
+use crate::ast::VariableType;
+use crate::wasm::Expression;
+use crate::{wasm, wasm_dollar};
+
+// A tuple is the address of a cell on the heap with a field for each element, in order.
+const FIELD_SIZE: usize = 8;
+// the collector has a bit for each field, so it can only find addresses in this many of them
+pub const MAX_ELEMENTS: usize = 32;
+
+// the address of a cell for a tuple of the types, with every field set to 0
+pub fn tuple(types: &[VariableType]) -> Expression {
+    let pointers = types
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| t.is_heap())
+        .fold(0u32, |pointers, (i, _)| pointers | 1 << i);
+    wasm!(
+        "call",
+        wasm_dollar!("_alloc"),
+        wasm!("i32.const", types.len() * FIELD_SIZE),
+        wasm!("i32.const", pointers as i32)
+    )
+}
+
+// the element of the tuple at `index`
+pub fn field(types: &[VariableType], index: usize, tuple: Expression) -> Expression {
+    wasm!(
+        format!("{}.load", types[index].to_wasm()),
+        format!("offset={}", index * FIELD_SIZE),
+        tuple
+    )
+}
+
+// sets the element of the tuple at `index` to `value`
+pub fn set_field(
+    types: &[VariableType],
+    index: usize,
+    tuple: Expression,
+    value: Expression,
+) -> Expression {
+    wasm!(
+        format!("{}.store", types[index].to_wasm()),
+        format!("offset={}", index * FIELD_SIZE),
+        tuple,
+        value
+    )
+}